@@ -2,6 +2,8 @@ cosmic-applet-button = Cosmic Button
 
 select-default = Select a device
 none-default = None
+yes = Yes
+no = No
 taildrop-files-sent = Files sent
 dir-chooser-title = Choose download directory
 
@@ -20,12 +22,41 @@ status-magic-dns-suffix = MagicDNS suffix
 status-advertise-exit = Advertise as exit node
 status-allow-lan-access = Exit Node LAN Access
 status-connect-toggle = Connect
+status-hostname = Hostname
+status-shields-up = Block incoming connections
+pause-title = Pause
+pause-15-min = 15 min
+pause-1-hour = 1 h
+pause-tomorrow = Until tomorrow
+pause-until = Paused until
+pause-resume = Resume
+shields-trust = Trust
+shields-untrust = Untrust
+hostname-save = Save
+hostname-invalid = Use 1-63 letters, digits or hyphens, not starting or ending with a hyphen
+hostname-default = Uses the system hostname
+
+flash-exit-node = Exit node
+flash-exit-node-off = Exit node turned off
+flash-account = Switched to account
+flash-prefs = Tailscale preferences changed
+flash-prefs-restored = Restored your preferences
+enforce-on = on
+enforce-off = off
+flash-files = New files waiting
+
+favorites-title = Favorites
+favorites-add = Add to favorites
+favorites-remove = Remove from favorites
+favorites-send = Send files
 
 subnets-title = Advertised subnet routes
 subnets-no-routes = No routes advertised
 subnets-remove = Remove
 subnets-cidr-placeholder = CIDR (e.g. 10.0.0.0/24)
 subnets-add = Add
+subnets-approved = Approved
+subnets-pending = Pending approval
 
 taildrop-send-title = Send files
 taildrop-send-to = Send to
@@ -38,6 +69,25 @@ taildrop-receive = Save to download directory
 
 devices-title = Peers
 devices-none = No peers found
+devices-no-match = No peers match the search and filters
+devices-search = Search name, DNS, IP, OS or tag
+devices-filter-online = Online
+devices-filter-exit-nodes = Exit nodes
+devices-filter-tagged = Tagged
+devices-filter-mullvad = Mullvad
+devices-filter-shared-in = Shared in
+devices-group-by = Group by
+devices-group-none = None
+devices-group-os = OS
+devices-group-tag = Tag
+devices-group-owner = Owner
+devices-group-untagged = Untagged
+devices-group-unknown = Unknown
+devices-sort-by = Sort by
+devices-sort-name = Name
+devices-sort-last-seen = Last seen
+devices-sort-traffic = Traffic
+devices-sort-latency = Latency
 devices-self = this device
 devices-os = OS
 devices-dns = DNS
@@ -55,12 +105,159 @@ devices-relayed = relayed
 devices-copy-ip = Copy IP
 devices-use-as-exit = Use as exit node
 devices-exit-current = Current exit node
+devices-watch = Watch
+devices-unwatch = Stop watching
+devices-copy-dns = Copy DNS name
+devices-open-ssh = Open SSH session
+devices-open-http = Open http://
+devices-open-https = Open https://
+devices-open-files = Open in file manager
+devices-key-expiry = Key expiry
+devices-key-expired = Expired
+devices-key-expires-on = { $date } ({ $days ->
+        [one] in 1 day
+       *[other] in { $days } days
+    })
+devices-key-expired-on = { $date } (expired)
+devices-subnet-routes = Routes
+devices-reauthenticate = Re-authenticate
+
+routes-title = Subnet routes from peers
+routes-none = No peer advertises subnet routes
+routes-active = Active
+routes-standby = Standby
+routes-not-accepted = Not accepted
+routes-offline = Offline
+routes-lan-conflict = Overlaps local network
+
+tka-title = Tailnet lock
+tka-unavailable = Tailnet lock status is unavailable
+tka-disabled = Tailnet lock is not enabled
+tka-status = Status
+tka-enabled = Enabled
+tka-this-node = This node
+tka-node-signed = Signed
+tka-node-unsigned = Not signed
+tka-signing-key = Signing key
+tka-signing-node = Can sign
+tka-signing-allowed = Allowed
+tka-signing-not-allowed = Not allowed
+tka-trusted-keys = Trusted keys
+tka-votes = Votes
+tka-awaiting-signature = Awaiting signature
+tka-none-awaiting = No peers awaiting signature
+tka-sign = Sign
+tka-signed-ok = Node signed
+tka-sign-error = Signing failed
 
 settings-auto-connect = Auto-connect on startup
+settings-enforce-prefs = Keep my SSH, routes, MagicDNS and exit node settings
 settings-icon-dynamic = Dynamic panel icon
 settings-notifications = Enable notifications
 settings-notify-connection = Notify on connection change
 settings-notify-files = Notify on incoming files
 settings-notify-device = Notify on new device
+settings-auto-shields-up = Block incoming on untrusted networks
+settings-notify-favorites = Notify when a favorite goes offline or online
+settings-presence-debounce = Report presence changes after (seconds)
+settings-notify-key-expiry = Remind before node key expiry
+settings-key-expiry-days = Reminder days before expiry
+settings-poll-interval = Status polling interval (seconds)
+settings-terminal = Terminal for SSH
+settings-ssh-user = Default SSH user
+settings-ssh-user-placeholder = Local user
 settings-download-dir = Download directory
 settings-change = Change…
+settings-file-title = Settings file
+settings-export = Export…
+settings-import = Import…
+settings-export-title = Export settings
+settings-import-title = Import settings
+settings-exported = Settings exported
+settings-imported = Settings imported
+settings-export-failed = Couldn't export settings
+settings-import-failed = Couldn't import settings
+
+notification-styles-title = Notification icons and sounds
+notification-sound-none = No sound
+notification-kind-connection = Connection changes
+notification-kind-new-device = New devices
+notification-kind-presence = Peers online or offline
+notification-kind-incoming-files = Incoming files
+notification-kind-files-sent = Files sent
+notification-kind-files-received = Files saved
+notification-kind-login = Login
+notification-kind-account-switched = Account switched
+notification-kind-key-expiry = Key expiry
+notification-kind-prefs-restored = Preferences restored
+
+accounts-title = Accounts
+accounts-current = Current
+accounts-delete = Delete
+accounts-delete-confirm = Delete account
+accounts-cancel = Cancel
+accounts-logout = Log out
+accounts-rename = Profile name
+accounts-control-url = Control server for new login
+accounts-login-failed = Login failed
+
+login-starting = Starting login…
+login-needs-login = Open this link to log in, or scan the code with your phone
+login-needs-approval = Logged in; waiting for an admin to approve this device
+login-done = Logged in
+login-open-browser = Open in browser
+login-open-failed = Couldn't open a browser
+
+quick-switch-none = No saved accounts
+
+shortcuts-title = Global shortcuts
+shortcuts-enable = Add shortcuts to COSMIC
+shortcuts-failed = Couldn't update COSMIC shortcuts
+
+auth-key-title = Log in with auth key
+auth-key-key = Auth key
+auth-key-tags = Advertised tags
+auth-key-submit = Log in
+
+schedule-title = Daily schedule
+schedule-connect = Connect at
+schedule-disconnect = Disconnect at
+schedule-invalid = Use HH:MM, e.g. 9:00, or leave empty
+
+rules-title = Network rules
+rules-current = Current network
+rules-none = No rules; the first matching rule applies when the network changes
+rules-match-ssid = Wi-Fi
+rules-match-gateway = Gateway MAC
+rules-match-interface = Interface
+rules-match-untrusted = Untrusted Wi-Fi
+rules-connect = Connect
+rules-disconnect = Disconnect
+rules-exit-keep = Keep exit node
+rules-exit-none = No exit node
+rules-exit-use = exit node
+
+node-title = This node
+node-app-connector = Run as app connector
+node-tags = Advertised tags
+node-no-tags = No tags advertised
+node-tag-invalid = Tags start with a letter and contain only letters, digits and dashes
+
+advanced-title = Advanced
+advanced-apply = Apply
+advanced-shields-up = Shields up (block incoming connections)
+advanced-no-snat = Disable SNAT for subnet routes
+advanced-netfilter = Netfilter mode
+advanced-netfilter-off = Off
+advanced-netfilter-nodivert = No divert
+advanced-netfilter-on = On
+advanced-posture-checking = Posture checking
+advanced-auto-update-check = Check for updates
+advanced-auto-update-apply = Install updates automatically
+advanced-web-client = Run web client
+advanced-operator = Operator user
+advanced-operator-invalid = Not a valid user name
+advanced-control-url = Control server URL
+advanced-control-url-invalid = Enter an http(s) URL, or leave empty for the default
+advanced-profile-name-empty = Profile name can't be empty
+advanced-logged-out = Logged out
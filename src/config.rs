@@ -1,27 +1,57 @@
-use std::fmt::Display;
-
-use cosmic::cosmic_config::{Config, ConfigGet, ConfigSet};
+use crate::{
+    network::NetworkRule,
+    notifications::{NotificationKind, NotificationStyle},
+};
+use cosmic::cosmic_config::{
+    Config, ConfigGet, ConfigSet, CosmicConfigEntry, cosmic_config_derive::CosmicConfigEntry,
+};
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use std::{collections::BTreeMap, fs, path::Path};
 
 pub const APP_ID: &str = "com.bhh32.GUIScaleApplet";
-pub const CONFIG_VERS: u64 = 2;
+
+/// Config schema version. Keep in sync with `#[version]` on
+/// [`AppPreferences`].
+///
+/// 1, 2: one kebab-case key per preference written through `Display`, so
+///       lists were comma-joined, network rules JSON, "no pause" 0, and
+///       missing keys were created as `""` whatever their type.
+/// 3:    `AppPreferences` as a typed entry, one key per field.
+pub const CONFIG_VERS: u64 = 3;
+
+/// Set in the current version's config once older versions were migrated.
+const MIGRATED_KEY: &str = "migrated";
 
 /// All user-configurable preferences, persisted across sessions.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Missing fields take their default when deserializing, so exported files
+/// from older versions still import.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, CosmicConfigEntry)]
+#[version = 3]
+#[serde(default)]
 pub struct AppPreferences {
     /// Selected exit node index.
     pub exit_node_idx: Option<usize>,
+    /// Name of the exit node chosen in the applet; `None` for no exit node.
+    pub exit_node: Option<String>,
     /// Allow LAN access on exit node.
     pub allow_lan: bool,
     /// SSH is enabled.
     pub ssh_enabled: bool,
     /// Routes accepted.
     pub routes_accepted: bool,
+    /// MagicDNS is enabled.
+    pub magic_dns: bool,
+    /// Re-apply the exit node, LAN access, SSH, route and MagicDNS choices
+    /// above when the daemon drifts from them, checked after connecting,
+    /// a daemon restart or a profile switch.
+    pub enforce_prefs: bool,
     /// Auto-connect on applet startup.
     pub auto_connect: bool,
     /// Custom download directory for TailDrop.
     pub download_dir: Option<String>,
-    /// Status pooling interval in seconds.
+    /// Status polling interval in seconds. Polling is a fallback for when
+    /// the IPN bus is unavailable, and slows down while it works.
     pub poll_interval_secs: u64,
     /// Notifications enabled/disabled.
     pub notifications_enabled: bool,
@@ -31,17 +61,82 @@ pub struct AppPreferences {
     pub notify_on_incoming_files: bool,
     /// Notify when a new device joins.
     pub notify_on_new_device: bool,
+    /// Remind before this node's key expires.
+    pub notify_on_key_expiry: bool,
+    /// Icon and sound per kind of notification; kinds not listed use the
+    /// Tailscale icon and no sound.
+    pub notification_styles: BTreeMap<NotificationKind, NotificationStyle>,
+    /// Days before key expiry at which to remind, e.g. `[7, 1]`.
+    pub key_expiry_reminder_days: Vec<u64>,
+    /// The last key expiry reminder: the key's expiry as Unix time and the
+    /// threshold reminded for, so restarts don't repeat it.
+    pub key_expiry_reminded: Option<(i64, u64)>,
     /// Panel icon style: "dynamic" (changes with status) or "static".
     pub icon_style: String,
+    /// Terminal used for SSH sessions; must accept `-e <command>`.
+    pub terminal: String,
+    /// Default user for SSH and SFTP; empty uses the local user.
+    pub ssh_user: String,
+    /// Node IDs of devices pinned to the Status tab.
+    pub favorites: Vec<String>,
+    /// Notify when a favorite device goes offline or comes back.
+    pub notify_on_favorite_presence: bool,
+    /// Node IDs of devices whose presence changes are notified.
+    pub watched_devices: Vec<String>,
+    /// How long a presence change must hold before it is notified.
+    pub presence_debounce_secs: u64,
+    /// Turn shields-up on for networks not in `trusted_networks` and off
+    /// for trusted ones, whenever the primary network changes.
+    pub auto_shields_up: bool,
+    /// NetworkManager connection names considered trusted.
+    pub trusted_networks: Vec<String>,
+    /// Connect/disconnect and exit node rules applied when the primary
+    /// network changes, in priority order.
+    pub network_rules: Vec<NetworkRule>,
+    /// Unix time at which a pause ends and Tailscale reconnects.
+    pub paused_until: Option<i64>,
+    /// Daily connect time as "HH:MM"; empty disables it.
+    pub schedule_connect: String,
+    /// Daily disconnect time as "HH:MM"; empty disables it.
+    pub schedule_disconnect: String,
+    /// Global shortcuts for toggling the connection and switching profiles
+    /// are registered with COSMIC.
+    pub global_shortcuts: bool,
+    /// Preferences remembered for each Tailscale profile, by profile ID,
+    /// and restored when it becomes current again.
+    pub profile_prefs: BTreeMap<String, ProfilePrefs>,
+}
+
+/// The preferences that follow the current Tailscale profile.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfilePrefs {
+    /// Name of the exit node in use; `None` for no exit node.
+    pub exit_node: Option<String>,
+    pub download_dir: Option<String>,
+    pub notifications_enabled: bool,
+}
+
+impl Default for ProfilePrefs {
+    fn default() -> Self {
+        Self {
+            exit_node: None,
+            download_dir: None,
+            notifications_enabled: true,
+        }
+    }
 }
 
 impl Default for AppPreferences {
     fn default() -> Self {
         Self {
             exit_node_idx: None,
+            exit_node: None,
             allow_lan: false,
             ssh_enabled: false,
             routes_accepted: false,
+            magic_dns: true,
+            enforce_prefs: false,
             auto_connect: false,
             download_dir: None,
             poll_interval_secs: 10,
@@ -49,98 +144,225 @@ impl Default for AppPreferences {
             notify_on_connection_change: true,
             notify_on_incoming_files: true,
             notify_on_new_device: true,
+            notify_on_key_expiry: true,
+            notification_styles: BTreeMap::new(),
+            key_expiry_reminder_days: vec![7, 1],
+            key_expiry_reminded: None,
             icon_style: "dynamic".to_string(),
+            terminal: "cosmic-term".to_string(),
+            ssh_user: String::new(),
+            favorites: Vec::new(),
+            notify_on_favorite_presence: false,
+            watched_devices: Vec::new(),
+            presence_debounce_secs: 30,
+            auto_shields_up: false,
+            trusted_networks: Vec::new(),
+            network_rules: Vec::new(),
+            paused_until: None,
+            schedule_connect: String::new(),
+            schedule_disconnect: String::new(),
+            global_shortcuts: false,
+            profile_prefs: BTreeMap::new(),
         }
     }
 }
 
-pub fn update_config<T>(config: Config, key: &str, value: T)
-where
-    T: Serialize + Display + Clone,
-{
-    let config_set = config.set(key, value.clone());
+/// Longest accepted reminder, in days before expiry.
+pub const MAX_REMINDER_DAYS: u64 = 365;
 
-    match config_set {
-        Ok(_) => println!("Config variable for {key} was set to {value}"),
-        Err(e) => eprintln!("Something went wrong setting {key} to {value}: {e}"),
-    }
+/// Parse a comma-separated list of reminder days, e.g. "7, 1". Days past
+/// [`MAX_REMINDER_DAYS`] are rejected.
+pub fn parse_reminder_days(input: &str) -> Option<Vec<u64>> {
+    let mut days = input
+        .split(',')
+        .map(str::trim)
+        .filter(|day| !day.is_empty())
+        .map(|day| {
+            day.parse::<u64>()
+                .ok()
+                .filter(|day| *day <= MAX_REMINDER_DAYS)
+        })
+        .collect::<Option<Vec<u64>>>()?;
 
-    let config_tx = config.transaction();
-    let tx_result = config_tx.commit();
+    days.sort_unstable_by(|a, b| b.cmp(a));
+    days.dedup();
+    Some(days)
+}
 
-    match tx_result {
-        Ok(_) => println!("Config transaction has been completed!"),
-        Err(e) => eprintln!("Something with the config transaction when wrong: {e}"),
-    }
+/// Format reminder days back into the "7, 1" form accepted by
+/// [`parse_reminder_days`].
+pub fn format_reminder_days(days: &[u64]) -> String {
+    days.iter()
+        .map(u64::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
-pub fn load_config<T>(key: &str, config_vers: u64) -> (Option<T>, String)
-where
-    T: DeserializeOwned,
-{
-    let config = match Config::new(APP_ID, config_vers) {
-        Ok(config) => config,
-        Err(e) => {
-            eprintln!("Loading config file had an error: {e}");
-            match Config::system(APP_ID, 1) {
-                Ok(config) => config,
-                Err(e) => {
-                    eprintln!("System config also failed: {e}");
-                    return (None, format!("Config error: {e}"));
-                }
+/// Load preferences from the current config version, migrating older
+/// versions the first time.
+pub fn load_preferences(config: &Config) -> AppPreferences {
+    let prefs = match AppPreferences::get_entry(config) {
+        Ok(prefs) => prefs,
+        Err((errors, prefs)) => {
+            // Keys that don't exist yet just keep their default.
+            for err in errors.iter().filter(|err| err.is_err()) {
+                eprintln!("Failed to load preference: {err}");
             }
+            prefs
+        }
+    };
+
+    if config.get::<bool>(MIGRATED_KEY).unwrap_or(false) {
+        return prefs;
+    }
+
+    let mut prefs = prefs;
+    for version in 1..CONFIG_VERS {
+        // Without a user config, the system-wide one holds the defaults.
+        let legacy = Config::new(APP_ID, version).or_else(|e| {
+            eprintln!("Failed to open config version {version}: {e}");
+            Config::system(APP_ID, version)
+        });
+        match legacy {
+            Ok(legacy) => migrate_legacy(&legacy, &mut prefs),
+            Err(e) => eprintln!("System config version {version} also failed: {e}"),
         }
+    }
+
+    // Write every key with its type, replacing defaults that were never
+    // stored.
+    if let Err(e) = prefs.write_entry(config) {
+        eprintln!("Failed to write migrated preferences: {e}");
+    } else if let Err(e) = config.set(MIGRATED_KEY, true) {
+        eprintln!("Failed to mark preferences as migrated: {e}");
+    }
+
+    prefs
+}
+
+/// Config keys, i.e. field names, whose values differ between `a` and `b`.
+pub fn changed_keys(a: &AppPreferences, b: &AppPreferences) -> Vec<String> {
+    let (Ok(serde_json::Value::Object(a)), Ok(serde_json::Value::Object(b))) =
+        (serde_json::to_value(a), serde_json::to_value(b))
+    else {
+        return Vec::new();
     };
+    a.into_iter()
+        .filter(|(key, val)| b.get(key) != Some(val))
+        .map(|(key, _)| key)
+        .collect()
+}
 
-    match config.get(key) {
-        Ok(value) => (Some(value), "".to_owned()),
-        Err(_) => {
-            update_config(config, key, "");
-            (None, "Created config for key".to_owned())
+/// `current` with the values of `keys` taken from `changed`, leaving every
+/// other preference as it is.
+pub fn merge_keys(
+    current: &AppPreferences,
+    changed: &AppPreferences,
+    keys: &[String],
+) -> AppPreferences {
+    let (Ok(serde_json::Value::Object(mut merged)), Ok(serde_json::Value::Object(changed))) =
+        (serde_json::to_value(current), serde_json::to_value(changed))
+    else {
+        return current.clone();
+    };
+    for key in keys {
+        if let Some(val) = changed.get(key) {
+            merged.insert(key.clone(), val.clone());
         }
     }
+    serde_json::from_value(serde_json::Value::Object(merged)).unwrap_or_else(|_| current.clone())
 }
 
-/// Load the full preferences struct from config.
-pub fn load_preferences() -> AppPreferences {
-    let mut prefs = AppPreferences::default();
+/// Settings file written by [`export_preferences`].
+#[derive(Serialize, Deserialize)]
+struct SettingsFile {
+    /// [`CONFIG_VERS`] of the applet that wrote the file.
+    version: u64,
+    preferences: AppPreferences,
+}
 
-    if let (Some(val), _) = load_config::<Option<usize>>("exit-node", CONFIG_VERS) {
+/// Write `prefs` to `path` as JSON.
+pub fn export_preferences(prefs: &AppPreferences, path: &Path) -> Result<(), String> {
+    let file = SettingsFile {
+        version: CONFIG_VERS,
+        preferences: prefs.clone(),
+    };
+    let json = serde_json::to_string_pretty(&file).map_err(|e| e.to_string())?;
+    fs::write(path, json).map_err(|e| e.to_string())
+}
+
+/// Read preferences written by [`export_preferences`]. State tied to the
+/// machine that wrote the file is not imported: a pause in progress, the
+/// exit node and per-account settings, which name its peers and accounts,
+/// and the key expiry reminder already shown.
+pub fn import_preferences(path: &Path) -> Result<AppPreferences, String> {
+    let json = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let file: SettingsFile = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+    if file.version > CONFIG_VERS {
+        return Err(format!(
+            "written by a newer version (config version {})",
+            file.version
+        ));
+    }
+
+    let mut prefs = file.preferences;
+    prefs.paused_until = None;
+    prefs.exit_node_idx = None;
+    prefs.exit_node = None;
+    prefs.profile_prefs.clear();
+    prefs.key_expiry_reminded = None;
+    Ok(prefs)
+}
+
+/// A legacy value, or `None` when it is missing or was written with the
+/// wrong type (e.g. the `""` placeholder).
+fn legacy<T: DeserializeOwned>(config: &Config, key: &str) -> Option<T> {
+    config.get(key).ok()
+}
+
+/// A legacy string, treating the `""` placeholder as unset.
+fn legacy_string(config: &Config, key: &str) -> Option<String> {
+    legacy::<String>(config, key).filter(|val| !val.is_empty())
+}
+
+/// Copy the preferences stored by a version 1 or 2 config into `prefs`.
+/// Versions share their keys, so later versions override earlier ones.
+/// Preferences added since are only stored in the current version.
+fn migrate_legacy(config: &Config, prefs: &mut AppPreferences) {
+    if let Some(val) = legacy(config, "exit-node") {
         prefs.exit_node_idx = val;
     }
-    if let (Some(val), _) = load_config::<bool>("allow-lan", CONFIG_VERS) {
+    if let Some(val) = legacy(config, "allow-lan") {
         prefs.allow_lan = val;
     }
-    if let (Some(val), _) = load_config::<bool>("ssh-enabled", CONFIG_VERS) {
+    if let Some(val) = legacy(config, "ssh-enabled") {
         prefs.ssh_enabled = val;
     }
-    if let (Some(val), _) = load_config::<bool>("routes-accepted", CONFIG_VERS) {
+    if let Some(val) = legacy(config, "routes-accepted") {
         prefs.routes_accepted = val;
     }
-    if let (Some(val), _) = load_config::<bool>("auto-connect", CONFIG_VERS) {
+    if let Some(val) = legacy(config, "auto-connect") {
         prefs.auto_connect = val;
     }
-    if let (Some(val), _) = load_config::<String>("download-dir", CONFIG_VERS) {
+    if let Some(val) = legacy_string(config, "download-dir") {
         prefs.download_dir = Some(val);
     }
-    if let (Some(val), _) = load_config::<u64>("poll-interval", CONFIG_VERS) {
+    if let Some(val) = legacy(config, "poll-interval") {
         prefs.poll_interval_secs = val;
     }
-    if let (Some(val), _) = load_config::<bool>("notifications-enabled", CONFIG_VERS) {
+    if let Some(val) = legacy(config, "notifications-enabled") {
         prefs.notifications_enabled = val;
     }
-    if let (Some(val), _) = load_config::<bool>("notify-connection", CONFIG_VERS) {
+    if let Some(val) = legacy(config, "notify-connection") {
         prefs.notify_on_connection_change = val;
     }
-    if let (Some(val), _) = load_config::<bool>("notify-files", CONFIG_VERS) {
+    if let Some(val) = legacy(config, "notify-files") {
         prefs.notify_on_incoming_files = val;
     }
-    if let (Some(val), _) = load_config::<bool>("notify-device", CONFIG_VERS) {
+    if let Some(val) = legacy(config, "notify-device") {
         prefs.notify_on_new_device = val;
     }
-    if let (Some(val), _) = load_config::<String>("icon-style", CONFIG_VERS) {
+    if let Some(val) = legacy_string(config, "icon-style") {
         prefs.icon_style = val;
     }
-
-    prefs
 }
@@ -0,0 +1,275 @@
+use crate::{
+    logic::{TailscaleState, send_files, set_connected, set_exit_node, switch_account},
+    tailscale_api::{TailscaleClient, TailscaleError},
+};
+use zbus::{Connection, connection, fdo, interface, object_server::SignalEmitter};
+
+/// Well-known bus name the applet owns on the session bus.
+pub const DBUS_NAME: &str = "com.bhh32.GUIScaleApplet";
+/// Object path the applet interface is served at.
+pub const DBUS_PATH: &str = "/com/bhh32/GUIScaleApplet";
+
+/// A peer as published over D-Bus: `(id, name, dns_name, ipv4, online)`.
+pub type DbusPeer = (String, String, String, String, bool);
+
+/// The slice of `TailscaleState` exposed as D-Bus properties.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DbusState {
+    pub connected: bool,
+    pub exit_node: String,
+    pub ip_v4: String,
+    pub ip_v6: String,
+    pub peers: Vec<DbusPeer>,
+    pub waiting_files: Vec<String>,
+    devices: Vec<(String, Vec<String>, Vec<String>)>,
+    /// Profile IDs in display order, for NextProfile.
+    accounts: Vec<String>,
+    current_account: Option<usize>,
+}
+
+impl From<&TailscaleState> for DbusState {
+    fn from(state: &TailscaleState) -> Self {
+        let exit_node = state
+            .devices
+            .iter()
+            .find(|dev| dev.is_exit_node && !dev.is_self)
+            .map(|dev| dev.name.clone())
+            .unwrap_or_default();
+
+        let peers = state
+            .devices
+            .iter()
+            .filter(|dev| !dev.is_self)
+            .map(|dev| {
+                (
+                    dev.id.clone(),
+                    dev.name.clone(),
+                    dev.dns_name.clone(),
+                    dev.tailscale_ips.first().cloned().unwrap_or_default(),
+                    dev.online,
+                )
+            })
+            .collect();
+
+        // Names, DNS names and IPs each peer can be addressed by from the
+        // SetExitNode and SendFiles methods.
+        let devices = state
+            .devices
+            .iter()
+            .filter(|dev| !dev.is_self)
+            .map(|dev| {
+                (
+                    dev.id.clone(),
+                    vec![dev.id.clone(), dev.name.clone(), dev.dns_name.clone()],
+                    dev.tailscale_ips.clone(),
+                )
+            })
+            .collect();
+
+        DbusState {
+            connected: state.connected,
+            exit_node,
+            ip_v4: state.ip_v4.clone(),
+            ip_v6: state.ip_v6.clone(),
+            peers,
+            waiting_files: state
+                .waiting_files
+                .iter()
+                .map(|file| file.name.clone())
+                .collect(),
+            devices,
+            accounts: state.accounts.iter().map(|acct| acct.id.clone()).collect(),
+            current_account: state.accounts.iter().position(|acct| acct.is_current),
+        }
+    }
+}
+
+impl DbusState {
+    /// Resolve a peer by ID, name, DNS name or Tailscale IP to
+    /// `(id, tailscale_ips)`.
+    fn find_peer(&self, peer: &str) -> Option<(&str, &[String])> {
+        let peer = peer.trim_end_matches('.');
+        self.devices
+            .iter()
+            .find(|(_, names, ips)| {
+                names
+                    .iter()
+                    .any(|name| !name.is_empty() && name.eq_ignore_ascii_case(peer))
+                    || ips.iter().any(|ip| ip == peer)
+            })
+            .map(|(id, _, ips)| (id.as_str(), ips.as_slice()))
+    }
+}
+
+/// The `com.bhh32.GUIScaleApplet1` interface.
+pub struct AppletInterface {
+    client: TailscaleClient,
+    state: DbusState,
+}
+
+fn failed(err: TailscaleError) -> fdo::Error {
+    fdo::Error::Failed(err.to_string())
+}
+
+#[interface(name = "com.bhh32.GUIScaleApplet1")]
+impl AppletInterface {
+    /// Connect to the tailnet.
+    async fn connect(&self) -> fdo::Result<()> {
+        set_connected(&self.client, true).await.map_err(failed)
+    }
+
+    /// Disconnect from the tailnet.
+    async fn disconnect(&self) -> fdo::Result<()> {
+        set_connected(&self.client, false).await.map_err(failed)
+    }
+
+    /// Connect when disconnected and vice versa.
+    async fn toggle_connection(&self) -> fdo::Result<()> {
+        set_connected(&self.client, !self.state.connected)
+            .await
+            .map_err(failed)
+    }
+
+    /// Switch to the profile after the current one, wrapping around.
+    async fn next_profile(&self) -> fdo::Result<()> {
+        if self.state.accounts.is_empty() {
+            return Err(fdo::Error::Failed("No saved profiles".to_string()));
+        }
+        let next = self
+            .state
+            .current_account
+            .map_or(0, |idx| (idx + 1) % self.state.accounts.len());
+
+        switch_account(&self.client, &self.state.accounts[next])
+            .await
+            .map_err(failed)
+    }
+
+    /// Use the peer matching `node` (name, DNS name, ID or IP) as exit node.
+    /// An empty string clears the exit node.
+    async fn set_exit_node(&self, node: &str) -> fdo::Result<()> {
+        let node_ip = if node.is_empty() {
+            String::new()
+        } else {
+            self.state
+                .find_peer(node)
+                .and_then(|(_, ips)| ips.first().cloned())
+                .ok_or_else(|| fdo::Error::InvalidArgs(format!("Unknown peer: {node}")))?
+        };
+
+        set_exit_node(&self.client, &node_ip).await.map_err(failed)
+    }
+
+    /// Send files to a peer via TailDrop.
+    async fn send_files(&self, peer: &str, paths: Vec<String>) -> fdo::Result<()> {
+        let (peer_id, _) = self
+            .state
+            .find_peer(peer)
+            .ok_or_else(|| fdo::Error::InvalidArgs(format!("Unknown peer: {peer}")))?;
+
+        match send_files(&self.client, peer_id, &paths).await {
+            None => Ok(()),
+            Some(err) => Err(fdo::Error::Failed(err)),
+        }
+    }
+
+    /// Emitted when new files land in the TailDrop inbox.
+    #[zbus(signal)]
+    async fn files_arrived(emitter: &SignalEmitter<'_>, files: Vec<String>) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    async fn connected(&self) -> bool {
+        self.state.connected
+    }
+
+    #[zbus(property)]
+    async fn exit_node(&self) -> String {
+        self.state.exit_node.clone()
+    }
+
+    #[zbus(property, name = "IPv4")]
+    async fn ip_v4(&self) -> String {
+        self.state.ip_v4.clone()
+    }
+
+    #[zbus(property, name = "IPv6")]
+    async fn ip_v6(&self) -> String {
+        self.state.ip_v6.clone()
+    }
+
+    #[zbus(property)]
+    async fn peers(&self) -> Vec<DbusPeer> {
+        self.state.peers.clone()
+    }
+
+    #[zbus(property)]
+    async fn waiting_files(&self) -> Vec<String> {
+        self.state.waiting_files.clone()
+    }
+}
+
+/// Claim the bus name and serve the applet interface on the session bus.
+/// The returned connection must be kept alive for the service to stay up.
+pub async fn serve(client: TailscaleClient) -> zbus::Result<Connection> {
+    let iface = AppletInterface {
+        client,
+        state: DbusState::default(),
+    };
+
+    connection::Builder::session()?
+        .name(DBUS_NAME)?
+        .serve_at(DBUS_PATH, iface)?
+        .build()
+        .await
+}
+
+/// Publish a new state snapshot, emitting `PropertiesChanged` for every
+/// property that changed and `FilesArrived` for newly waiting files.
+pub async fn publish_state(conn: &Connection, state: DbusState) -> zbus::Result<()> {
+    let iface_ref = conn
+        .object_server()
+        .interface::<_, AppletInterface>(DBUS_PATH)
+        .await?;
+    let mut iface = iface_ref.get_mut().await;
+
+    if iface.state == state {
+        return Ok(());
+    }
+
+    let old = std::mem::replace(&mut iface.state, state);
+    let emitter = iface_ref.signal_emitter();
+
+    if old.connected != iface.state.connected {
+        iface.connected_changed(emitter).await?;
+    }
+    if old.exit_node != iface.state.exit_node {
+        iface.exit_node_changed(emitter).await?;
+    }
+    // zbus derives the `*_changed` helper names from the D-Bus property
+    // names, hence `i_pv4` for "IPv4".
+    if old.ip_v4 != iface.state.ip_v4 {
+        iface.i_pv4_changed(emitter).await?;
+    }
+    if old.ip_v6 != iface.state.ip_v6 {
+        iface.i_pv6_changed(emitter).await?;
+    }
+    if old.peers != iface.state.peers {
+        iface.peers_changed(emitter).await?;
+    }
+    if old.waiting_files != iface.state.waiting_files {
+        iface.waiting_files_changed(emitter).await?;
+
+        let arrived: Vec<String> = iface
+            .state
+            .waiting_files
+            .iter()
+            .filter(|file| !old.waiting_files.contains(file))
+            .cloned()
+            .collect();
+        if !arrived.is_empty() {
+            AppletInterface::files_arrived(emitter, arrived).await?;
+        }
+    }
+
+    Ok(())
+}
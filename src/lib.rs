@@ -1,6 +1,11 @@
 pub mod config;
+pub mod dbus;
 pub mod localize;
 pub mod logic;
+pub mod network;
 pub mod notifications;
+pub mod routes;
+pub mod shortcuts;
+pub mod state_diff;
 pub mod tailscale_api;
 pub mod window;
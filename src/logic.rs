@@ -1,11 +1,21 @@
-use crate::tailscale_api::{PeerStatus, TailscaleClient, TsResult};
+use crate::{
+    routes::Cidr,
+    tailscale_api::{
+        AuthKeyError, IpnState, LoginEvent, NetworkLockStatus, PeerStatus, PrefEdit, Prefs,
+        TailscaleClient, TsResult,
+    },
+};
+use chrono::{DateTime, Days, Local, NaiveTime, TimeZone, Utc};
 use std::{
-    env,
+    cmp::Ordering,
+    collections::HashMap,
+    env, io,
     io::Write,
     path::Path,
     process::{Command, Stdio},
-    time::Duration,
+    time::{Duration, Instant},
 };
+use url::Url;
 
 // Re-export the error types so window.rs can use them.
 pub use crate::tailscale_api::{PingResult, TailscaleError, WaitingFile};
@@ -22,10 +32,20 @@ pub struct DeviceInfo {
     pub exit_node_option: bool,
     pub tags: Vec<String>,
     pub relay: String,
+    pub direct: bool,
     pub rx_bytes: u64,
     pub tx_bytes: u64,
     pub last_seen: String,
     pub is_self: bool,
+    pub key_expiry: Option<DateTime<Utc>>,
+    pub key_expired: bool,
+    pub run_ssh: bool,
+    pub is_mullvad: bool,
+    pub shared_in: bool,
+    pub owner: String,
+    pub primary_routes: Vec<String>,
+    /// Approved subnet routes the peer advertises, primary or not.
+    pub subnet_routes: Vec<String>,
 }
 
 impl From<(&PeerStatus, bool)> for DeviceInfo {
@@ -48,10 +68,43 @@ impl From<(&PeerStatus, bool)> for DeviceInfo {
             exit_node_option: peer.exit_node_option,
             tags: peer.tags.clone().unwrap_or_default(),
             relay: peer.relay.clone(),
+            direct: !peer.cur_addr.is_empty(),
             rx_bytes: peer.rx_bytes,
             tx_bytes: peer.tx_bytes,
             last_seen: peer.last_seen.clone(),
             is_self,
+            key_expiry: peer
+                .key_expiry
+                .as_deref()
+                .and_then(|expiry| DateTime::parse_from_rfc3339(expiry).ok())
+                .map(|expiry| expiry.with_timezone(&Utc)),
+            key_expired: peer.expired,
+            run_ssh: peer
+                .ssh_host_keys
+                .as_ref()
+                .is_some_and(|keys| !keys.is_empty()),
+            is_mullvad: peer.is_mullvad,
+            shared_in: peer.sharee_node,
+            owner: String::new(),
+            primary_routes: peer.primary_routes.clone().unwrap_or_default(),
+            // AllowedIPs also holds the node's own addresses and, for exit
+            // nodes, the default routes.
+            subnet_routes: peer
+                .allowed_ips
+                .iter()
+                .flatten()
+                .filter(|route| {
+                    route.parse::<Cidr>().is_ok_and(|cidr| {
+                        let own_ip = cidr.is_host()
+                            && peer
+                                .tailscale_ips
+                                .iter()
+                                .any(|ip| ip.parse() == Ok(cidr.addr()));
+                        !cidr.is_default() && !own_ip
+                    })
+                })
+                .cloned()
+                .collect(),
         }
     }
 }
@@ -65,6 +118,70 @@ pub struct AccountInfo {
     pub is_current: bool,
 }
 
+/// A key trusted to sign nodes under tailnet lock.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedKeyInfo {
+    pub key: String,
+    pub votes: u64,
+    pub is_self: bool,
+}
+
+/// A peer that cannot connect until its node key is signed.
+#[derive(Debug, Clone, Default)]
+pub struct LockedOutPeer {
+    pub name: String,
+    pub node_key: String,
+    pub tailscale_ips: Vec<String>,
+}
+
+/// Tailnet lock (TKA) state as seen by this node.
+#[derive(Debug, Clone, Default)]
+pub struct TailnetLockInfo {
+    pub enabled: bool,
+    pub public_key: String,
+    pub node_key_signed: bool,
+    /// This node's key is one of the trusted keys, so it can sign peers.
+    pub is_signing_node: bool,
+    pub trusted_keys: Vec<TrustedKeyInfo>,
+    pub locked_out: Vec<LockedOutPeer>,
+}
+
+impl From<NetworkLockStatus> for TailnetLockInfo {
+    fn from(status: NetworkLockStatus) -> Self {
+        let trusted_keys: Vec<TrustedKeyInfo> = status
+            .trusted_keys
+            .unwrap_or_default()
+            .into_iter()
+            .map(|key| TrustedKeyInfo {
+                is_self: !status.public_key.is_empty() && key.key == status.public_key,
+                key: key.key,
+                votes: key.votes,
+            })
+            .collect();
+
+        let mut locked_out: Vec<LockedOutPeer> = status
+            .filtered_peers
+            .unwrap_or_default()
+            .into_iter()
+            .map(|peer| LockedOutPeer {
+                name: peer.name.split('.').next().unwrap_or_default().to_string(),
+                node_key: peer.node_key,
+                tailscale_ips: peer.tailscale_ips,
+            })
+            .collect();
+        locked_out.sort_by_key(|peer| peer.name.to_lowercase());
+
+        TailnetLockInfo {
+            enabled: status.enabled,
+            is_signing_node: trusted_keys.iter().any(|key| key.is_self),
+            public_key: status.public_key,
+            node_key_signed: status.node_key_signed,
+            trusted_keys,
+            locked_out,
+        }
+    }
+}
+
 /// Full snapshot of Tailscale state.
 #[derive(Debug, Clone, Default)]
 pub struct TailscaleState {
@@ -77,12 +194,24 @@ pub struct TailscaleState {
     pub ip_v4: String,
     pub ip_v6: String,
     pub dns_suffix: String,
+    /// Hostname this node registers with; the OS hostname unless overridden.
+    pub hostname: String,
+    /// This node's MagicDNS name without the trailing dot.
+    pub dns_name: String,
     pub devices: Vec<DeviceInfo>,
     pub device_names: Vec<String>,
     pub exit_node_options: Vec<DeviceInfo>,
     pub accounts: Vec<AccountInfo>,
     pub advertised_routes: Vec<String>,
+    /// Advertised routes the admin has approved.
+    pub approved_routes: Vec<String>,
+    pub advertised_tags: Vec<String>,
+    pub app_connector: bool,
     pub waiting_files: Vec<WaitingFile>,
+    pub self_key_expiry: Option<DateTime<Utc>>,
+    pub tailnet_lock: Option<TailnetLockInfo>,
+    /// Raw daemon prefs, for settings without a dedicated field above.
+    pub prefs: Prefs,
 }
 
 /// Fetch a complete snapshot of Tailscale state.
@@ -106,15 +235,25 @@ pub async fn fetch_state(client: &TailscaleClient) -> TsResult<TailscaleState> {
     // iteration order — sort peers by name so the dropdown index stays
     // stable across fetches (otherwise the selection appears to "cycle"
     // whenever a new IPN notify triggers a refetch).
+    let with_owner = |peer: &PeerStatus, is_self: bool| {
+        let mut dev = DeviceInfo::from((peer, is_self));
+        dev.owner = status
+            .user
+            .get(&peer.user_id.to_string())
+            .map(|user| user.login_name.clone())
+            .unwrap_or_default();
+        dev
+    };
+
     let mut devices = Vec::new();
     if let Some(ref self_peer) = status.self_node {
-        devices.push(DeviceInfo::from((self_peer, true)));
+        devices.push(with_owner(self_peer, true));
     }
 
     let mut peers: Vec<DeviceInfo> = status
         .peer
         .values()
-        .map(|peer| DeviceInfo::from((peer, false)))
+        .map(|peer| with_owner(peer, false))
         .collect();
     peers.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
     devices.extend(peers);
@@ -154,6 +293,22 @@ pub async fn fetch_state(client: &TailscaleClient) -> TsResult<TailscaleState> {
         })
         .unwrap_or_default();
 
+    // Approved routes show up in this node's AllowedIPs. Compare parsed
+    // networks since the control plane may format them differently.
+    let allowed_ips: Vec<Cidr> = self_node
+        .and_then(|node| node.allowed_ips.as_ref())
+        .map(|ips| ips.iter().filter_map(|ip| ip.parse().ok()).collect())
+        .unwrap_or_default();
+    let approved_routes: Vec<String> = advertised_routes
+        .iter()
+        .filter(|route| {
+            route
+                .parse::<Cidr>()
+                .is_ok_and(|route| allowed_ips.contains(&route))
+        })
+        .cloned()
+        .collect();
+
     // Accounts
     let profiles = client.profiles().await.unwrap_or_default();
     let current_profile = client.current_profile().await.ok();
@@ -185,8 +340,27 @@ pub async fn fetch_state(client: &TailscaleClient) -> TsResult<TailscaleState> {
         .map(|tailnet| tailnet.magic_dns_suffix.clone())
         .unwrap_or_default();
 
+    let hostname = if prefs.hostname.is_empty() {
+        self_node
+            .map(|node| node.host_name.clone())
+            .unwrap_or_default()
+    } else {
+        prefs.hostname.clone()
+    };
+    let dns_name = self_node
+        .map(|node| node.dns_name.trim_end_matches('.').to_string())
+        .unwrap_or_default();
+
     let waiting_files = client.waiting_files().await.unwrap_or_default();
 
+    // Older daemons don't have the TKA endpoints; treat that as "unknown".
+    let tailnet_lock = client.tka_status().await.ok().map(TailnetLockInfo::from);
+
+    let self_key_expiry = devices
+        .iter()
+        .find(|dev| dev.is_self)
+        .and_then(|dev| dev.key_expiry);
+
     Ok(TailscaleState {
         connected: prefs.want_running,
         ssh_enabled: prefs.run_ssh,
@@ -197,15 +371,334 @@ pub async fn fetch_state(client: &TailscaleClient) -> TsResult<TailscaleState> {
         ip_v4,
         ip_v6,
         dns_suffix,
+        hostname,
+        dns_name,
         devices,
         device_names,
         exit_node_options,
         accounts,
         advertised_routes,
+        approved_routes,
+        advertised_tags: prefs.advertise_tags.clone().unwrap_or_default(),
+        app_connector: prefs.app_connector.advertise,
         waiting_files,
+        self_key_expiry,
+        tailnet_lock,
+        prefs,
     })
 }
 
+/// A filter narrowing the Devices tab; active filters must all match.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeviceFilter {
+    Online,
+    ExitNode,
+    Tagged,
+    Mullvad,
+    SharedIn,
+}
+
+impl DeviceFilter {
+    pub const ALL: [DeviceFilter; 5] = [
+        DeviceFilter::Online,
+        DeviceFilter::ExitNode,
+        DeviceFilter::Tagged,
+        DeviceFilter::Mullvad,
+        DeviceFilter::SharedIn,
+    ];
+
+    pub fn matches(self, dev: &DeviceInfo) -> bool {
+        match self {
+            DeviceFilter::Online => dev.online,
+            DeviceFilter::ExitNode => dev.exit_node_option,
+            DeviceFilter::Tagged => !dev.tags.is_empty(),
+            DeviceFilter::Mullvad => dev.is_mullvad,
+            DeviceFilter::SharedIn => dev.shared_in,
+        }
+    }
+}
+
+/// How the Devices tab groups peers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DeviceGrouping {
+    #[default]
+    None,
+    Os,
+    Tag,
+    Owner,
+}
+
+impl DeviceGrouping {
+    pub const ALL: [DeviceGrouping; 4] = [
+        DeviceGrouping::None,
+        DeviceGrouping::Os,
+        DeviceGrouping::Tag,
+        DeviceGrouping::Owner,
+    ];
+}
+
+/// How the Devices tab orders peers within a group.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DeviceSort {
+    #[default]
+    Name,
+    LastSeen,
+    Traffic,
+    Latency,
+}
+
+impl DeviceSort {
+    pub const ALL: [DeviceSort; 4] = [
+        DeviceSort::Name,
+        DeviceSort::LastSeen,
+        DeviceSort::Traffic,
+        DeviceSort::Latency,
+    ];
+}
+
+impl DeviceInfo {
+    /// Case-insensitive match against name, DNS name, IPs, OS and tags.
+    pub fn matches_query(&self, query: &str) -> bool {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return true;
+        }
+
+        [&self.name, &self.dns_name, &self.os]
+            .into_iter()
+            .chain(&self.tailscale_ips)
+            .chain(&self.tags)
+            .any(|field| field.to_lowercase().contains(&query))
+    }
+
+    fn last_seen_at(&self) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(&self.last_seen)
+            .ok()
+            .map(|seen| seen.with_timezone(&Utc))
+    }
+}
+
+/// Compute the Devices tab layout: indices into `devices` that match `query`
+/// and every filter in `filters`, grouped and sorted. Groups are returned as
+/// `(key, indices)`; the key is empty for the "no OS/tag/owner" group, which
+/// sorts last. Each device is in exactly one group, and there are no empty
+/// groups, so nothing matched when the result is empty.
+///
+/// `latencies` maps a Tailscale IP to its last measured ping latency in
+/// seconds; peers never pinged sort after those that were.
+pub fn device_view(
+    devices: &[DeviceInfo],
+    query: &str,
+    filters: &[DeviceFilter],
+    grouping: DeviceGrouping,
+    sort: DeviceSort,
+    latencies: &HashMap<String, f64>,
+) -> Vec<(String, Vec<usize>)> {
+    let mut matching: Vec<usize> = devices
+        .iter()
+        .enumerate()
+        .filter(|(_, dev)| dev.matches_query(query))
+        .filter(|(_, dev)| filters.iter().all(|filter| filter.matches(dev)))
+        .map(|(idx, _)| idx)
+        .collect();
+
+    let latency = |dev: &DeviceInfo| {
+        dev.tailscale_ips
+            .first()
+            .and_then(|ip| latencies.get(ip))
+            .copied()
+    };
+
+    // This device always leads; ties fall back to name order.
+    matching.sort_by(|&a, &b| {
+        let (a, b) = (&devices[a], &devices[b]);
+        let by_key = match sort {
+            DeviceSort::Name => Ordering::Equal,
+            DeviceSort::LastSeen => match (a.online, b.online) {
+                (true, false) => Ordering::Less,
+                (false, true) => Ordering::Greater,
+                _ => b.last_seen_at().cmp(&a.last_seen_at()),
+            },
+            DeviceSort::Traffic => (b.rx_bytes + b.tx_bytes).cmp(&(a.rx_bytes + a.tx_bytes)),
+            DeviceSort::Latency => match (latency(a), latency(b)) {
+                (Some(a), Some(b)) => a.total_cmp(&b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            },
+        };
+
+        b.is_self
+            .cmp(&a.is_self)
+            .then(by_key)
+            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+    });
+
+    let mut groups: Vec<(String, Vec<usize>)> = Vec::new();
+    for idx in matching {
+        let dev = &devices[idx];
+        let key = match grouping {
+            DeviceGrouping::None => String::new(),
+            DeviceGrouping::Os => dev.os.clone(),
+            DeviceGrouping::Owner => dev.owner.clone(),
+            // A device with several tags is listed once, under the first
+            // tag alphabetically.
+            DeviceGrouping::Tag => dev.tags.iter().min().cloned().unwrap_or_default(),
+        };
+
+        match groups.iter_mut().find(|(group, _)| *group == key) {
+            Some((_, members)) => members.push(idx),
+            None => groups.push((key, vec![idx])),
+        }
+    }
+
+    groups.sort_by(|(a, _), (b, _)| match (a.is_empty(), b.is_empty()) {
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        _ => a.to_lowercase().cmp(&b.to_lowercase()),
+    });
+    groups
+}
+
+/// The parts of a peer's state the watch list cares about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Presence {
+    pub online: bool,
+    pub relay: String,
+    pub direct: bool,
+}
+
+impl From<&DeviceInfo> for Presence {
+    fn from(dev: &DeviceInfo) -> Self {
+        Presence {
+            online: dev.online,
+            relay: dev.relay.clone(),
+            direct: dev.direct,
+        }
+    }
+}
+
+/// A notable change in a watched peer's presence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PresenceChange {
+    Online,
+    Offline,
+    /// Now relayed through a different DERP region.
+    RelayChanged(String),
+    /// Switched from a relayed to a direct connection.
+    Direct,
+}
+
+impl Presence {
+    /// Changes between `self` and `new`. Going on- or offline also moves the
+    /// relay and path, so only the online state is reported then.
+    pub fn changes_to(&self, new: &Presence) -> Vec<PresenceChange> {
+        if self.online != new.online {
+            return vec![if new.online {
+                PresenceChange::Online
+            } else {
+                PresenceChange::Offline
+            }];
+        }
+        if !new.online {
+            return Vec::new();
+        }
+
+        let mut changes = Vec::new();
+        if !self.relay.is_empty() && !new.relay.is_empty() && self.relay != new.relay {
+            changes.push(PresenceChange::RelayChanged(new.relay.clone()));
+        }
+        if !self.direct && new.direct {
+            changes.push(PresenceChange::Direct);
+        }
+        changes
+    }
+}
+
+/// Debounces presence changes of watched peers.
+///
+/// A peer's new presence must hold for the debounce period before it is
+/// reported, so a device flapping on and off a network is only reported once
+/// it settles, and not at all if it returns to where it was.
+#[derive(Debug, Default)]
+pub struct PresenceWatch {
+    settled: HashMap<String, Presence>,
+    pending: HashMap<String, (Presence, Instant)>,
+}
+
+impl PresenceWatch {
+    /// Record the presence observed for peer `id`. The first sighting of a
+    /// peer only establishes its baseline.
+    pub fn observe(&mut self, id: &str, presence: Presence, now: Instant) {
+        let Some(settled) = self.settled.get(id) else {
+            self.settled.insert(id.to_string(), presence);
+            return;
+        };
+
+        if *settled == presence {
+            self.pending.remove(id);
+        } else if self
+            .pending
+            .get(id)
+            .is_none_or(|(pending, _)| *pending != presence)
+        {
+            self.pending.insert(id.to_string(), (presence, now));
+        }
+    }
+
+    /// Has a baseline been recorded for peer `id`.
+    pub fn knows(&self, id: &str) -> bool {
+        self.settled.contains_key(id)
+    }
+
+    /// Forget peer `id`, e.g. once it left the tailnet.
+    pub fn forget(&mut self, id: &str) {
+        self.settled.remove(id);
+        self.pending.remove(id);
+    }
+
+    /// Forget peers no longer being watched.
+    pub fn retain(&mut self, ids: &[String]) {
+        self.settled.retain(|id, _| ids.contains(id));
+        self.pending.retain(|id, _| ids.contains(id));
+    }
+
+    /// When the earliest pending change will have held for `debounce`, if
+    /// any change is waiting it out.
+    pub fn next_due(&self, debounce: Duration) -> Option<Instant> {
+        self.pending
+            .values()
+            .map(|(_, since)| *since + debounce)
+            .min()
+    }
+
+    /// Report the changes that have held for at least `debounce`.
+    pub fn settle(&mut self, now: Instant, debounce: Duration) -> Vec<(String, PresenceChange)> {
+        let due: Vec<String> = self
+            .pending
+            .iter()
+            .filter(|(_, (_, since))| now.duration_since(*since) >= debounce)
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        let mut changes = Vec::new();
+        for id in due {
+            if let Some((presence, _)) = self.pending.remove(&id) {
+                if let Some(settled) = self.settled.get(&id) {
+                    changes.extend(
+                        settled
+                            .changes_to(&presence)
+                            .into_iter()
+                            .map(|change| (id.clone(), change)),
+                    );
+                }
+                self.settled.insert(id, presence);
+            }
+        }
+        changes
+    }
+}
+
 /// Connect to the tailnet.
 pub async fn connect(client: &TailscaleClient) -> TsResult<()> {
     client.connect().await?;
@@ -263,36 +756,272 @@ pub async fn set_exit_node_allow_lan(client: &TailscaleClient, allow: bool) -> T
     Ok(())
 }
 
+/// Set this node's hostname.
+pub async fn set_hostname(client: &TailscaleClient, hostname: &str) -> TsResult<()> {
+    client.set_hostname(hostname).await?;
+    Ok(())
+}
+
+/// Is `label` a valid DNS label: 1-63 letters, digits or hyphens, not
+/// starting or ending with a hyphen.
+pub fn valid_dns_label(label: &str) -> bool {
+    (1..=63).contains(&label.len())
+        && label
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '-')
+        && !label.starts_with('-')
+        && !label.ends_with('-')
+}
+
+/// The MagicDNS name `hostname` would get under `dns_suffix`. Tailscale
+/// lowercases hostnames for DNS.
+pub fn preview_fqdn(hostname: &str, dns_suffix: &str) -> String {
+    let label = hostname.to_lowercase();
+    if dns_suffix.is_empty() {
+        label
+    } else {
+        format!("{label}.{}", dns_suffix.trim_matches('.'))
+    }
+}
+
+/// Apply a single typed preference change.
+pub async fn set_pref(client: &TailscaleClient, edit: PrefEdit) -> TsResult<()> {
+    client.set_pref(edit).await?;
+    Ok(())
+}
+
+/// Is `user` a valid Unix user name for the operator setting.
+pub fn valid_operator_user(user: &str) -> bool {
+    let mut chars = user.chars();
+    user.len() <= 32
+        && chars
+            .next()
+            .is_some_and(|ch| ch.is_ascii_lowercase() || ch == '_')
+        && chars.all(|ch| ch.is_ascii_lowercase() || ch.is_ascii_digit() || ch == '_' || ch == '-')
+}
+
+/// Is `url` usable as a control server URL: empty for the default, or an
+/// absolute http(s) URL with a host.
+pub fn valid_control_url(url: &str) -> bool {
+    url.is_empty()
+        || url::Url::parse(url)
+            .is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.host_str().is_some())
+}
+
+/// Set the tags this node advertises.
+pub async fn set_advertise_tags(client: &TailscaleClient, tags: Vec<String>) -> TsResult<()> {
+    client.set_advertise_tags(tags).await?;
+    Ok(())
+}
+
+/// Enable/disable app connector mode.
+pub async fn set_app_connector(client: &TailscaleClient, advertise: bool) -> TsResult<()> {
+    client.set_app_connector(advertise).await?;
+    Ok(())
+}
+
+/// Normalize a tag to its `tag:name` form. Names must start with a letter
+/// and contain only letters, digits and dashes.
+pub fn parse_tag(input: &str) -> Option<String> {
+    let input = input.trim();
+    let name = input.strip_prefix("tag:").unwrap_or(input);
+    let valid = name.starts_with(|ch: char| ch.is_ascii_alphabetic())
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '-');
+
+    valid.then(|| format!("tag:{name}"))
+}
+
 /// Switch to a different account/profile.
 pub async fn switch_account(client: &TailscaleClient, profile_id: &str) -> TsResult<()> {
     client.switch_profile(profile_id).await
 }
 
-/// Open login page in browser for a new account.
+/// Sign a locked-out peer's node key with this node's tailnet lock key.
+pub async fn sign_node(client: &TailscaleClient, node_key: &str) -> TsResult<()> {
+    client.tka_sign(node_key).await
+}
+
+/// Log the current account out. The profile stays saved and can log in
+/// again.
+pub async fn logout_account(client: &TailscaleClient) -> TsResult<()> {
+    client.logout().await
+}
+
+/// Delete a saved account/profile from this device.
+pub async fn delete_account(client: &TailscaleClient, profile_id: &str) -> TsResult<()> {
+    client.delete_profile(profile_id).await
+}
+
+/// Start logging in a new account.
 ///
-/// Drives the IPN-bus flow end-to-end: subscribe to the notify bus, ask the
-/// daemon to start an interactive login, then `xdg-open` the URL the daemon
-/// publishes. Inside a Flatpak sandbox, `xdg-open` is routed through the
-/// OpenURI portal so the host browser receives the URL.
-pub async fn login_new_account(client: &TailscaleClient) -> TsResult<()> {
-    let watcher = client.wait_for_browse_url(std::time::Duration::from_secs(30));
+/// Switches to an empty profile unless the current one is still unsaved,
+/// points it at `control_url` when one is given (e.g. a Headscale server),
+/// then asks the daemon for an interactive login. The auth URL and the
+/// login's progress arrive on the IPN bus; see
+/// [`TailscaleClient::watch_login`].
+pub async fn login_new_account(client: &TailscaleClient, control_url: &str) -> TsResult<()> {
+    if !client.current_profile().await?.id.is_empty() {
+        client.new_profile().await?;
+    }
+    if !control_url.is_empty() {
+        client
+            .set_pref(PrefEdit::ControlUrl(control_url.to_string()))
+            .await?;
+    }
+    client.login_interactive().await
+}
+
+/// Re-authenticate the current profile before its node key expires.
+///
+/// Uses the same IPN-bus flow as [`login_new_account`]; an interactive login
+/// on an already-registered profile renews its node key.
+pub async fn reauthenticate(client: &TailscaleClient) -> TsResult<()> {
+    client.login_interactive().await
+}
+
+/// A new account login with a pre-auth key. Empty fields keep the daemon's
+/// current value.
+#[derive(Debug, Clone, Default)]
+pub struct AuthKeyLogin {
+    pub auth_key: String,
+    pub hostname: String,
+    pub advertise_tags: Vec<String>,
+    pub control_url: String,
+}
+
+/// Log a new account in with a pre-auth key, the way `tailscale up
+/// --auth-key` does: start the backend with the key and the requested prefs,
+/// then start the login, which uses the key instead of a browser. The
+/// login's progress, and a rejected key, arrive on the IPN bus.
+pub async fn login_with_auth_key(
+    client: &TailscaleClient,
+    login: &AuthKeyLogin,
+) -> Result<(), AuthKeyError> {
+    let auth_key = login.auth_key.trim();
+    if auth_key.is_empty() {
+        return Err(AuthKeyError::Empty);
+    }
+
+    if !client.current_profile().await?.id.is_empty() {
+        client.new_profile().await?;
+    }
+
+    // Start replaces every pref, so edit the daemon's own copy to keep the
+    // fields we don't model.
+    let mut prefs = client.prefs_json().await?;
+    prefs["WantRunning"] = true.into();
+    if !login.hostname.is_empty() {
+        prefs["Hostname"] = login.hostname.clone().into();
+    }
+    if !login.advertise_tags.is_empty() {
+        prefs["AdvertiseTags"] = login.advertise_tags.clone().into();
+    }
+    if !login.control_url.is_empty() {
+        prefs["ControlURL"] = login.control_url.clone().into();
+    }
+
+    client.start(auth_key, &prefs).await?;
     client.login_interactive().await?;
-    let url = watcher.await?;
-    open_url(&url)
+    Ok(())
+}
+
+/// Where an interactive login is at, from the events on the IPN bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LoginStage {
+    /// Asked the daemon to log in; no auth URL yet.
+    #[default]
+    Starting,
+    /// Waiting for the user to visit the auth URL.
+    NeedsLogin,
+    /// Logged in; an admin has to approve the device.
+    NeedsMachineAuth,
+    Done,
+}
+
+impl LoginStage {
+    /// The stage after `event`. The bus replays the current state first, so
+    /// `Running` only counts as done once the login has been seen waiting
+    /// for the user.
+    pub fn next(self, event: &LoginEvent) -> Self {
+        match (self, event) {
+            (_, LoginEvent::Finished) => LoginStage::Done,
+            (_, LoginEvent::BrowseToUrl(_)) | (_, LoginEvent::State(IpnState::NeedsLogin)) => {
+                LoginStage::NeedsLogin
+            }
+            (_, LoginEvent::State(IpnState::NeedsMachineAuth)) => LoginStage::NeedsMachineAuth,
+            (
+                LoginStage::NeedsLogin | LoginStage::NeedsMachineAuth,
+                LoginEvent::State(IpnState::Running),
+            ) => LoginStage::Done,
+            (stage, _) => stage,
+        }
+    }
 }
 
-fn open_url(url: &str) -> TsResult<()> {
-    use std::process::{Command, Stdio};
-    Command::new("xdg-open")
-        .arg(url)
+/// Start `command` detached from the applet, waiting for it on a blocking
+/// thread so it doesn't linger as a zombie once it exits.
+fn spawn_detached(command: &mut Command) -> io::Result<()> {
+    let mut child = command
         .stdin(Stdio::null())
         .stdout(Stdio::null())
         .stderr(Stdio::null())
-        .spawn()
-        .map(|_| ())
+        .spawn()?;
+    tokio::task::spawn_blocking(move || child.wait());
+    Ok(())
+}
+
+/// Open a URL with the desktop's default handler.
+pub fn open_url(url: &str) -> TsResult<()> {
+    spawn_detached(Command::new("xdg-open").arg(url))
         .map_err(|e| TailscaleError::RequestFailed(format!("xdg-open: {e}")))
 }
 
+/// Build the `user@host` SSH destination; an empty user leaves it to ssh.
+pub fn ssh_destination(user: &str, host: &str) -> String {
+    if user.is_empty() {
+        host.to_string()
+    } else {
+        format!("{user}@{host}")
+    }
+}
+
+/// `sftp://` URL for browsing `host` as `user` in the file manager; an
+/// empty user leaves it to the file manager. `None` if `host` isn't a valid
+/// host name.
+pub fn sftp_url(user: &str, host: &str) -> Option<String> {
+    let mut url = Url::parse("sftp://localhost/").ok()?;
+    url.set_host(Some(host)).ok()?;
+    url.set_username(user).ok()?;
+    Some(url.into())
+}
+
+/// Open an SSH session to `host` in the configured terminal. Peers running
+/// Tailscale SSH are reached with `tailscale ssh` so no keys are needed.
+///
+/// Inside a Flatpak the terminal is started on the host via `flatpak-spawn`,
+/// since neither the terminal nor `ssh` exist in the sandbox.
+pub fn open_ssh_session(
+    terminal: &str,
+    user: &str,
+    host: &str,
+    tailscale_ssh: bool,
+) -> Result<(), String> {
+    let mut args: Vec<String> = Vec::new();
+    if Path::new("/.flatpak-info").exists() {
+        args.extend(["flatpak-spawn".to_string(), "--host".to_string()]);
+    }
+    args.extend([terminal.to_string(), "-e".to_string()]);
+    if tailscale_ssh {
+        args.push("tailscale".to_string());
+    }
+    args.extend(["ssh".to_string(), ssh_destination(user, host)]);
+
+    spawn_detached(Command::new(&args[0]).args(&args[1..]))
+        .map_err(|err| format!("Failed to launch {terminal}: {err}"))
+}
+
 /// Ping a device by IP.
 pub async fn ping_device(client: &TailscaleClient, ip: &str) -> TsResult<PingResult> {
     client.ping(ip, "disco").await
@@ -428,6 +1157,92 @@ pub fn format_bytes(bytes: u64) -> String {
     }
 }
 
+/// How long to pause Tailscale for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PauseDuration {
+    Minutes15,
+    Hour,
+    /// Until `resume_at` the next day.
+    UntilTomorrow,
+}
+
+impl PauseDuration {
+    pub const ALL: [PauseDuration; 3] = [
+        PauseDuration::Minutes15,
+        PauseDuration::Hour,
+        PauseDuration::UntilTomorrow,
+    ];
+}
+
+/// When a pause started at `now` ends.
+pub fn pause_until(
+    duration: PauseDuration,
+    now: DateTime<Local>,
+    resume_at: NaiveTime,
+) -> DateTime<Local> {
+    match duration {
+        PauseDuration::Minutes15 => now + chrono::Duration::minutes(15),
+        PauseDuration::Hour => now + chrono::Duration::hours(1),
+        PauseDuration::UntilTomorrow => now
+            .date_naive()
+            .checked_add_days(Days::new(1))
+            .and_then(|day| {
+                Local
+                    .from_local_datetime(&day.and_time(resume_at))
+                    .earliest()
+            })
+            .unwrap_or(now + chrono::Duration::days(1)),
+    }
+}
+
+/// Parse a daily schedule time like "9:00" or "18:30".
+pub fn parse_schedule_time(time: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(time.trim(), "%H:%M").ok()
+}
+
+/// Did the daily `time` occur in `(since, now]`.
+pub fn schedule_crossed(time: NaiveTime, since: DateTime<Local>, now: DateTime<Local>) -> bool {
+    since
+        .date_naive()
+        .iter_days()
+        .take_while(|day| *day <= now.date_naive())
+        .filter_map(|day| Local.from_local_datetime(&day.and_time(time)).earliest())
+        .any(|at| since < at && at <= now)
+}
+
+/// Whole days until `expiry`, rounded up so a key expiring in 30 hours is
+/// reported as 2 days away rather than 1.
+pub fn days_until(expiry: DateTime<Utc>, now: DateTime<Utc>) -> u64 {
+    let secs = (expiry - now).num_seconds().max(0) as u64;
+    secs.div_ceil(24 * 60 * 60)
+}
+
+/// Pick the reminder threshold (in days) that is due for a key expiring at
+/// `expiry`, if any.
+///
+/// `thresholds` are the configured "days before expiry" values and
+/// `last_reminded` is the threshold the user was last reminded for, so each
+/// threshold fires at most once per key.
+pub fn due_key_expiry_reminder(
+    expiry: DateTime<Utc>,
+    now: DateTime<Utc>,
+    thresholds: &[u64],
+    last_reminded: Option<u64>,
+) -> Option<u64> {
+    if expiry <= now {
+        return None;
+    }
+
+    let remaining = (expiry - now).num_seconds() as u64;
+
+    thresholds
+        .iter()
+        .copied()
+        .filter(|days| remaining <= days.saturating_mul(24 * 60 * 60))
+        .filter(|days| last_reminded.is_none_or(|last| *days < last))
+        .min()
+}
+
 /// Get the default download directory for the current user.
 pub fn default_download_dir() -> String {
     if let Ok(home) = env::var("HOME") {
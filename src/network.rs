@@ -0,0 +1,383 @@
+use cosmic::iced::futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use zbus::{Connection, proxy, zvariant::OwnedObjectPath};
+
+const WIFI_CONNECTION_TYPE: &str = "802-11-wireless";
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager",
+    default_service = "org.freedesktop.NetworkManager",
+    default_path = "/org/freedesktop/NetworkManager"
+)]
+trait NetworkManager {
+    #[zbus(property)]
+    fn primary_connection(&self) -> zbus::Result<OwnedObjectPath>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Connection.Active",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+trait ActiveConnection {
+    #[zbus(property)]
+    fn id(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn uuid(&self) -> zbus::Result<String>;
+
+    #[zbus(property, name = "Type")]
+    fn connection_type(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn devices(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+
+    /// The access point for Wi-Fi connections.
+    #[zbus(property)]
+    fn specific_object(&self) -> zbus::Result<OwnedObjectPath>;
+
+    #[zbus(property)]
+    fn ip4_config(&self) -> zbus::Result<OwnedObjectPath>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Device",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+trait Device {
+    #[zbus(property)]
+    fn interface(&self) -> zbus::Result<String>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.AccessPoint",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+trait AccessPoint {
+    #[zbus(property)]
+    fn ssid(&self) -> zbus::Result<Vec<u8>>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.IP4Config",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+trait Ip4Config {
+    #[zbus(property)]
+    fn gateway(&self) -> zbus::Result<String>;
+}
+
+/// The NetworkManager connection carrying the default route.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkInfo {
+    /// Connection name, e.g. the Wi-Fi SSID or "Wired connection 1".
+    pub id: String,
+    pub uuid: String,
+    /// NetworkManager connection type, e.g. "802-11-wireless".
+    pub kind: String,
+    /// Wi-Fi network name, for wireless connections.
+    pub ssid: Option<String>,
+    /// Interface of the connection's first device, e.g. "wlp2s0".
+    pub interface: String,
+    /// MAC address of the IPv4 gateway, lowercase, when it is in the
+    /// neighbour table.
+    pub gateway_mac: Option<String>,
+}
+
+impl NetworkInfo {
+    pub fn is_wifi(&self) -> bool {
+        self.kind == WIFI_CONNECTION_TYPE
+    }
+}
+
+/// The current primary connection, or `None` when offline.
+pub async fn primary_network(conn: &Connection) -> zbus::Result<Option<NetworkInfo>> {
+    let nm = NetworkManagerProxy::new(conn).await?;
+    let path = nm.primary_connection().await?;
+    active_connection(conn, path).await
+}
+
+async fn active_connection(
+    conn: &Connection,
+    path: OwnedObjectPath,
+) -> zbus::Result<Option<NetworkInfo>> {
+    // NetworkManager reports "/" when there is no primary connection.
+    if path.as_str() == "/" {
+        return Ok(None);
+    }
+
+    let active = ActiveConnectionProxy::builder(conn)
+        .path(path)?
+        .build()
+        .await?;
+    let kind = active.connection_type().await?;
+
+    // The details below are best effort; a rule keyed on one that is
+    // missing simply doesn't match.
+    let interface = connection_interface(conn, &active)
+        .await
+        .unwrap_or_default();
+    let ssid = if kind == WIFI_CONNECTION_TYPE {
+        connection_ssid(conn, &active).await
+    } else {
+        None
+    };
+    let gateway_mac = connection_gateway_mac(conn, &active).await;
+
+    Ok(Some(NetworkInfo {
+        id: active.id().await?,
+        uuid: active.uuid().await?,
+        kind,
+        ssid,
+        interface,
+        gateway_mac,
+    }))
+}
+
+/// Interface name of the connection's first device.
+async fn connection_interface(
+    conn: &Connection,
+    active: &ActiveConnectionProxy<'_>,
+) -> Option<String> {
+    let device = active.devices().await.ok()?.into_iter().next()?;
+    let device = DeviceProxy::builder(conn)
+        .path(device)
+        .ok()?
+        .build()
+        .await
+        .ok()?;
+    device.interface().await.ok()
+}
+
+/// SSID of the access point a Wi-Fi connection is using.
+async fn connection_ssid(conn: &Connection, active: &ActiveConnectionProxy<'_>) -> Option<String> {
+    let ap = AccessPointProxy::builder(conn)
+        .path(active.specific_object().await.ok()?)
+        .ok()?
+        .build()
+        .await
+        .ok()?;
+    let ssid = ap.ssid().await.ok()?;
+    Some(String::from_utf8_lossy(&ssid).into_owned())
+}
+
+/// MAC address of the connection's IPv4 gateway.
+async fn connection_gateway_mac(
+    conn: &Connection,
+    active: &ActiveConnectionProxy<'_>,
+) -> Option<String> {
+    let ip4_config = active.ip4_config().await.ok()?;
+    if ip4_config.as_str() == "/" {
+        return None;
+    }
+    let ip4 = Ip4ConfigProxy::builder(conn)
+        .path(ip4_config)
+        .ok()?
+        .build()
+        .await
+        .ok()?;
+    neighbour_mac(&ip4.gateway().await.ok()?)
+}
+
+/// MAC address of `ip` from the kernel ARP table.
+fn neighbour_mac(ip: &str) -> Option<String> {
+    // IP address  HW type  Flags  HW address  Mask  Device
+    let table = fs::read_to_string("/proc/net/arp").ok()?;
+    table
+        .lines()
+        .skip(1)
+        .map(|line| line.split_whitespace().collect::<Vec<_>>())
+        .find(|cols| cols.len() >= 4 && cols[0] == ip && cols[3] != "00:00:00:00:00:00")
+        .map(|cols| cols[3].to_lowercase())
+}
+
+/// What a [`NetworkRule`] matches on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NetworkMatch {
+    Ssid(String),
+    GatewayMac(String),
+    Interface(String),
+    /// Any Wi-Fi network whose connection isn't in the trusted list.
+    UntrustedWifi,
+}
+
+impl NetworkMatch {
+    fn matches(&self, network: &NetworkInfo, trusted_networks: &[String]) -> bool {
+        match self {
+            NetworkMatch::Ssid(ssid) => network.ssid.as_ref() == Some(ssid),
+            NetworkMatch::GatewayMac(mac) => network
+                .gateway_mac
+                .as_ref()
+                .is_some_and(|gateway| gateway.eq_ignore_ascii_case(mac)),
+            NetworkMatch::Interface(iface) => network.interface == *iface,
+            NetworkMatch::UntrustedWifi => {
+                network.is_wifi() && !trusted_networks.contains(&network.id)
+            }
+        }
+    }
+}
+
+/// What to do with the exit node when a rule applies.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExitNodeRule {
+    /// Leave the current exit node alone.
+    #[default]
+    Keep,
+    /// Stop using an exit node.
+    Clear,
+    /// Use the peer with this name.
+    Use(String),
+}
+
+/// Tailscale state to apply when a rule applies.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RuleAction {
+    Connect(ExitNodeRule),
+    Disconnect,
+}
+
+/// "On this network, do that." The first matching rule wins.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkRule {
+    pub matcher: NetworkMatch,
+    pub action: RuleAction,
+}
+
+/// The first rule matching `network`.
+pub fn matching_rule<'a>(
+    rules: &'a [NetworkRule],
+    network: &NetworkInfo,
+    trusted_networks: &[String],
+) -> Option<&'a NetworkRule> {
+    rules
+        .iter()
+        .find(|rule| rule.matcher.matches(network, trusted_networks))
+}
+
+/// Watch NetworkManager on the system bus and invoke `on_change` with the
+/// primary connection, once on start and again whenever it changes. Returns
+/// when the stream ends or errors; callers are responsible for
+/// reconnect/backoff.
+pub async fn watch_primary_network<F>(mut on_change: F) -> zbus::Result<()>
+where
+    F: FnMut(Option<NetworkInfo>) + Send,
+{
+    let conn = Connection::system().await?;
+    let nm = NetworkManagerProxy::new(&conn).await?;
+    let mut changes = nm.receive_primary_connection_changed().await;
+
+    on_change(primary_network(&conn).await?);
+
+    while let Some(change) = changes.next().await {
+        let path = change.get().await?;
+        on_change(active_connection(&conn, path).await?);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wifi(ssid: &str) -> NetworkInfo {
+        NetworkInfo {
+            id: ssid.to_string(),
+            uuid: format!("{ssid}-uuid"),
+            kind: WIFI_CONNECTION_TYPE.to_string(),
+            ssid: Some(ssid.to_string()),
+            interface: "wlp2s0".to_string(),
+            gateway_mac: Some("aa:bb:cc:dd:ee:ff".to_string()),
+        }
+    }
+
+    fn wired() -> NetworkInfo {
+        NetworkInfo {
+            id: "Wired connection 1".to_string(),
+            uuid: "wired-uuid".to_string(),
+            kind: "802-3-ethernet".to_string(),
+            ssid: None,
+            interface: "enp3s0".to_string(),
+            gateway_mac: None,
+        }
+    }
+
+    fn rule(matcher: NetworkMatch, action: RuleAction) -> NetworkRule {
+        NetworkRule { matcher, action }
+    }
+
+    #[test]
+    fn matchers() {
+        let home = wifi("Home");
+        let matches = |matcher: NetworkMatch, network: &NetworkInfo| {
+            matcher.matches(network, &["Home".to_string()])
+        };
+
+        assert!(matches(NetworkMatch::Ssid("Home".to_string()), &home));
+        assert!(!matches(NetworkMatch::Ssid("Cafe".to_string()), &home));
+        assert!(!matches(NetworkMatch::Ssid("Home".to_string()), &wired()));
+
+        assert!(matches(
+            NetworkMatch::GatewayMac("AA:BB:CC:DD:EE:FF".to_string()),
+            &home
+        ));
+        assert!(!matches(
+            NetworkMatch::GatewayMac("aa:bb:cc:dd:ee:00".to_string()),
+            &home
+        ));
+        assert!(!matches(
+            NetworkMatch::GatewayMac("aa:bb:cc:dd:ee:ff".to_string()),
+            &wired()
+        ));
+
+        assert!(matches(
+            NetworkMatch::Interface("enp3s0".to_string()),
+            &wired()
+        ));
+        assert!(!matches(
+            NetworkMatch::Interface("enp3s0".to_string()),
+            &home
+        ));
+    }
+
+    #[test]
+    fn untrusted_wifi_skips_trusted_and_wired() {
+        let trusted = ["Home".to_string()];
+        assert!(!NetworkMatch::UntrustedWifi.matches(&wifi("Home"), &trusted));
+        assert!(NetworkMatch::UntrustedWifi.matches(&wifi("Cafe"), &trusted));
+        assert!(!NetworkMatch::UntrustedWifi.matches(&wired(), &trusted));
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let rules = [
+            rule(
+                NetworkMatch::Ssid("Office".to_string()),
+                RuleAction::Disconnect,
+            ),
+            rule(
+                NetworkMatch::UntrustedWifi,
+                RuleAction::Connect(ExitNodeRule::Use("vpn".to_string())),
+            ),
+            rule(
+                NetworkMatch::Interface("wlp2s0".to_string()),
+                RuleAction::Connect(ExitNodeRule::Clear),
+            ),
+        ];
+        let trusted = ["Home".to_string(), "Office".to_string()];
+
+        let action = |network: &NetworkInfo| {
+            matching_rule(&rules, network, &trusted).map(|rule| rule.action.clone())
+        };
+        assert_eq!(action(&wifi("Office")), Some(RuleAction::Disconnect));
+        assert_eq!(
+            action(&wifi("Cafe")),
+            Some(RuleAction::Connect(ExitNodeRule::Use("vpn".to_string())))
+        );
+        assert_eq!(
+            action(&wifi("Home")),
+            Some(RuleAction::Connect(ExitNodeRule::Clear))
+        );
+        assert_eq!(action(&wired()), None);
+    }
+}
@@ -1,50 +1,488 @@
-use notify_rust::{Notification, Timeout};
+use crate::logic::PresenceChange;
+use cosmic::cosmic_config::{Config, ConfigGet};
+use notify_rust::{Notification, Timeout, Urgency};
+use serde::{Deserialize, Serialize};
+#[cfg(test)]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+};
 
 const APP_NAME: &str = "GUI Scale Applet";
-const ICON: &str = "tailscale-icon";
+/// Icon for notifications whose kind has no icon set.
+pub const DEFAULT_ICON: &str = "tailscale-icon";
+const TIMEOUT: Timeout = Timeout::Milliseconds(5000);
 
-/// Send a desktop notification
-fn send_notification(summary: &str, body: &str) {
-    if let Err(e) = Notification::new()
-        .appname(APP_NAME)
-        .summary(summary)
-        .body(body)
-        .icon(ICON)
-        .timeout(Timeout::Milliseconds(5000))
-        .show()
-    {
-        eprintln!("Failed to show notification: {e}");
+/// cosmic-notifications' config; its `do_not_disturb` key mutes popups.
+const COSMIC_NOTIFICATIONS_ID: &str = "com.system76.CosmicNotifications";
+const COSMIC_NOTIFICATIONS_VERS: u64 = 1;
+const DO_NOT_DISTURB_KEY: &str = "do_not_disturb";
+
+/// What a notification is about. Decides its urgency and which icon and
+/// sound it uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum NotificationKind {
+    Connection,
+    NewDevice,
+    Presence,
+    IncomingFiles,
+    FilesSent,
+    FilesReceived,
+    Login,
+    AccountSwitched,
+    KeyExpiry,
+    PrefsRestored,
+}
+
+impl NotificationKind {
+    pub const ALL: [NotificationKind; 10] = [
+        NotificationKind::Connection,
+        NotificationKind::NewDevice,
+        NotificationKind::Presence,
+        NotificationKind::IncomingFiles,
+        NotificationKind::FilesSent,
+        NotificationKind::FilesReceived,
+        NotificationKind::Login,
+        NotificationKind::AccountSwitched,
+        NotificationKind::KeyExpiry,
+        NotificationKind::PrefsRestored,
+    ];
+
+    /// Critical notifications are shown even with Do-Not-Disturb on.
+    pub fn urgency(self) -> Urgency {
+        match self {
+            NotificationKind::KeyExpiry => Urgency::Critical,
+            NotificationKind::Connection
+            | NotificationKind::IncomingFiles
+            | NotificationKind::Login
+            | NotificationKind::AccountSwitched
+            | NotificationKind::PrefsRestored => Urgency::Normal,
+            NotificationKind::NewDevice
+            | NotificationKind::Presence
+            | NotificationKind::FilesSent
+            | NotificationKind::FilesReceived => Urgency::Low,
+        }
     }
 }
 
-pub fn notify_connection_change(connected: bool) {
-    let body = if connected {
-        "Tailscale connected"
-    } else {
-        "Tailscale disconnected"
-    };
-    send_notification("Tailscale", body);
+/// Icon and sound for one kind of notification.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationStyle {
+    /// Icon name or path; empty for the Tailscale icon.
+    pub icon: String,
+    /// Sound theme name, e.g. "message-new-instant"; empty for no sound.
+    pub sound: String,
+}
+
+/// A notification ready to be shown.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notice {
+    pub kind: NotificationKind,
+    /// Notices with the same tag replace each other instead of stacking.
+    pub tag: String,
+    pub summary: String,
+    pub body: String,
+    pub icon: String,
+    pub sound: Option<String>,
+    pub urgency: Urgency,
 }
 
-pub fn notify_new_device(device_name: &str) {
-    send_notification("New Tailscale device", device_name);
+/// Where notifications are shown.
+pub trait Notifier: Send + Sync {
+    /// Show `notice`, replacing the one shown before with the same tag.
+    fn show(&self, notice: Notice);
+
+    /// Show `notice` with `(id, label)` actions and block until it is
+    /// closed. Returns the ID of the chosen action.
+    fn show_with_actions(&self, notice: Notice, actions: &[(&str, &str)]) -> Option<String>;
+
+    /// Whether the desktop asked not to be disturbed.
+    fn do_not_disturb(&self) -> bool;
 }
 
-pub fn notify_incoming_files() {
-    send_notification("TailDrop", "Incoming files are waiting");
+/// Shows notifications through the desktop's notification server.
+#[derive(Default)]
+pub struct DesktopNotifier {
+    /// Server IDs of the notifications shown last, by tag.
+    ids: Mutex<HashMap<String, u32>>,
 }
 
-pub fn notify_files_sent(device: &str, count: usize) {
-    let body = format!("Sent {count} file(s) to {device}");
-    send_notification("TailDrop", &body);
+impl DesktopNotifier {
+    fn build(&self, notice: &Notice) -> Notification {
+        let mut notification = Notification::new();
+        notification
+            .appname(APP_NAME)
+            .summary(&notice.summary)
+            .body(&notice.body)
+            .icon(&notice.icon)
+            .urgency(notice.urgency)
+            .timeout(TIMEOUT);
+        if let Some(sound) = &notice.sound {
+            notification.sound_name(sound);
+        }
+        if let Some(id) = self.ids.lock().unwrap().get(&notice.tag) {
+            notification.id(*id);
+        }
+        notification
+    }
+
+    fn remember(&self, tag: String, id: u32) {
+        self.ids.lock().unwrap().insert(tag, id);
+    }
 }
 
-pub fn notify_files_received(path: &str) {
-    let body = format!("Saved incoming files to {path}");
-    send_notification("TailDrop", &body);
+impl Notifier for DesktopNotifier {
+    fn show(&self, notice: Notice) {
+        match self.build(&notice).show() {
+            Ok(handle) => self.remember(notice.tag, handle.id()),
+            Err(e) => eprintln!("Failed to show notification: {e}"),
+        }
+    }
+
+    fn show_with_actions(&self, notice: Notice, actions: &[(&str, &str)]) -> Option<String> {
+        let mut notification = self.build(&notice);
+        for (id, label) in actions {
+            notification.action(id, label);
+        }
+        notification.timeout(Timeout::Never);
+
+        let handle = match notification.show() {
+            Ok(handle) => handle,
+            Err(e) => {
+                eprintln!("Failed to show notification: {e}");
+                return None;
+            }
+        };
+        self.remember(notice.tag, handle.id());
+
+        let mut chosen = None;
+        handle.wait_for_action(|action| {
+            if actions.iter().any(|(id, _)| *id == action) {
+                chosen = Some(action.to_string());
+            }
+        });
+        chosen
+    }
+
+    fn do_not_disturb(&self) -> bool {
+        Config::new(COSMIC_NOTIFICATIONS_ID, COSMIC_NOTIFICATIONS_VERS)
+            .ok()
+            .and_then(|config| config.get::<bool>(DO_NOT_DISTURB_KEY).ok())
+            .unwrap_or(false)
+    }
+}
+
+/// Keeps notifications instead of showing them, to check what the applet
+/// would show.
+#[cfg(test)]
+#[derive(Default)]
+pub struct RecordingNotifier {
+    /// The notices currently shown, oldest first.
+    shown: Mutex<Vec<Notice>>,
+    do_not_disturb: AtomicBool,
+    /// Action "chosen" for notices that offer it.
+    action: Option<String>,
+}
+
+#[cfg(test)]
+impl RecordingNotifier {
+    /// A recorder that chooses `action` whenever it is offered.
+    pub fn choosing(action: &str) -> Self {
+        Self {
+            action: Some(action.to_string()),
+            ..Self::default()
+        }
+    }
+
+    pub fn set_do_not_disturb(&self, on: bool) {
+        self.do_not_disturb.store(on, Ordering::Relaxed);
+    }
+
+    pub fn shown(&self) -> Vec<Notice> {
+        self.shown.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+impl Notifier for RecordingNotifier {
+    fn show(&self, notice: Notice) {
+        let mut shown = self.shown.lock().unwrap();
+        match shown.iter_mut().find(|shown| shown.tag == notice.tag) {
+            Some(replaced) => *replaced = notice,
+            None => shown.push(notice),
+        }
+    }
+
+    fn show_with_actions(&self, notice: Notice, actions: &[(&str, &str)]) -> Option<String> {
+        self.show(notice);
+        self.action
+            .clone()
+            .filter(|action| actions.iter().any(|(id, _)| id == action))
+    }
+
+    fn do_not_disturb(&self) -> bool {
+        self.do_not_disturb.load(Ordering::Relaxed)
+    }
+}
+
+/// The applet's notifications, styled per kind and handed to a
+/// [`Notifier`].
+#[derive(Clone)]
+pub struct Notifications {
+    notifier: Arc<dyn Notifier>,
+    styles: BTreeMap<NotificationKind, NotificationStyle>,
+}
+
+impl Notifications {
+    pub fn new(
+        notifier: Arc<dyn Notifier>,
+        styles: BTreeMap<NotificationKind, NotificationStyle>,
+    ) -> Self {
+        Self { notifier, styles }
+    }
+
+    pub fn set_styles(&mut self, styles: BTreeMap<NotificationKind, NotificationStyle>) {
+        self.styles = styles;
+    }
+
+    /// A notice of `kind`. Notices about different subjects, e.g. peers,
+    /// are shown side by side; a new one about the same subject replaces
+    /// the old one.
+    fn notice(
+        &self,
+        kind: NotificationKind,
+        subject: Option<&str>,
+        summary: &str,
+        body: &str,
+    ) -> Notice {
+        let style = self.styles.get(&kind);
+        let tag = match subject {
+            Some(subject) => format!("{kind:?}:{subject}"),
+            None => format!("{kind:?}"),
+        };
+        Notice {
+            kind,
+            tag,
+            summary: summary.to_string(),
+            body: body.to_string(),
+            icon: style
+                .map(|style| style.icon.clone())
+                .filter(|icon| !icon.is_empty())
+                .unwrap_or_else(|| DEFAULT_ICON.to_string()),
+            sound: style
+                .map(|style| style.sound.clone())
+                .filter(|sound| !sound.is_empty()),
+            urgency: kind.urgency(),
+        }
+    }
+
+    /// Only critical notices get through Do-Not-Disturb.
+    fn muted(&self, notice: &Notice) -> bool {
+        notice.urgency != Urgency::Critical && self.notifier.do_not_disturb()
+    }
+
+    fn send(&self, notice: Notice) {
+        if !self.muted(&notice) {
+            self.notifier.show(notice);
+        }
+    }
+
+    pub fn connection_change(&self, connected: bool) {
+        let body = if connected {
+            "Tailscale connected"
+        } else {
+            "Tailscale disconnected"
+        };
+        self.send(self.notice(NotificationKind::Connection, None, "Tailscale", body));
+    }
+
+    pub fn new_device(&self, device_name: &str) {
+        self.send(self.notice(
+            NotificationKind::NewDevice,
+            Some(device_name),
+            "New Tailscale device",
+            device_name,
+        ));
+    }
+
+    pub fn presence_change(&self, device_name: &str, change: &PresenceChange) {
+        let body = match change {
+            PresenceChange::Online => format!("{device_name} is back online"),
+            PresenceChange::Offline => format!("{device_name} went offline"),
+            PresenceChange::RelayChanged(relay) => {
+                format!("{device_name} is now relayed via {relay}")
+            }
+            PresenceChange::Direct => format!("{device_name} is now connected directly"),
+        };
+        self.send(self.notice(
+            NotificationKind::Presence,
+            Some(device_name),
+            "Tailscale",
+            &body,
+        ));
+    }
+
+    pub fn incoming_files(&self, count: usize) {
+        let body = format!("{count} incoming file(s) waiting");
+        self.send(self.notice(NotificationKind::IncomingFiles, None, "TailDrop", &body));
+    }
+
+    pub fn files_sent(&self, device: &str, count: usize) {
+        let body = format!("Sent {count} file(s) to {device}");
+        self.send(self.notice(NotificationKind::FilesSent, Some(device), "TailDrop", &body));
+    }
+
+    pub fn files_received(&self, path: &str) {
+        let body = format!("Saved incoming files to {path}");
+        self.send(self.notice(NotificationKind::FilesReceived, None, "TailDrop", &body));
+    }
+
+    pub fn login_succeeded(&self) {
+        self.send(self.notice(NotificationKind::Login, None, "Tailscale", "Logged in"));
+    }
+
+    pub fn account_switched(&self, account: &str) {
+        let body = format!("Switched to account: {account}");
+        self.send(self.notice(NotificationKind::AccountSwitched, None, "Tailscale", &body));
+    }
+
+    /// Report preferences that were re-applied after the daemon drifted
+    /// from them, e.g. "Enable SSH: on, Exit node: None".
+    pub fn prefs_restored(&self, corrections: &str) {
+        self.send(self.notice(
+            NotificationKind::PrefsRestored,
+            None,
+            "Tailscale preferences restored",
+            corrections,
+        ));
+    }
+
+    /// Warn that this node's key expires soon. Blocks until the
+    /// notification is closed and returns `true` if the user chose
+    /// "Re-authenticate", so call it from a blocking task.
+    pub fn key_expiry(&self, days_left: u64) -> bool {
+        let body = match days_left {
+            0 => "This device's node key expires today".to_string(),
+            1 => "This device's node key expires in 1 day".to_string(),
+            days => format!("This device's node key expires in {days} days"),
+        };
+
+        let notice = self.notice(
+            NotificationKind::KeyExpiry,
+            None,
+            "Tailscale key expiry",
+            &body,
+        );
+        if self.muted(&notice) {
+            return false;
+        }
+        self.notifier
+            .show_with_actions(notice, &[("reauth", "Re-authenticate")])
+            .is_some_and(|action| action == "reauth")
+    }
 }
 
-pub fn notify_account_switched(account: &str) {
-    let body = format!("Switched to account: {account}");
-    send_notification("Tailscale", &body);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notifications(recorder: &Arc<RecordingNotifier>) -> Notifications {
+        Notifications::new(recorder.clone(), BTreeMap::new())
+    }
+
+    #[test]
+    fn same_subject_replaces() {
+        let recorder = Arc::new(RecordingNotifier::default());
+        let notifications = notifications(&recorder);
+
+        notifications.presence_change("laptop", &PresenceChange::Offline);
+        notifications.presence_change("phone", &PresenceChange::Offline);
+        notifications.presence_change("laptop", &PresenceChange::Online);
+
+        let bodies: Vec<String> = recorder
+            .shown()
+            .into_iter()
+            .map(|notice| notice.body)
+            .collect();
+        assert_eq!(bodies, ["laptop is back online", "phone went offline"]);
+    }
+
+    #[test]
+    fn urgency_follows_kind() {
+        let recorder = Arc::new(RecordingNotifier::default());
+        let notifications = notifications(&recorder);
+
+        notifications.connection_change(true);
+        notifications.new_device("laptop");
+        notifications.key_expiry(3);
+
+        let urgencies: Vec<(NotificationKind, Urgency)> = recorder
+            .shown()
+            .into_iter()
+            .map(|notice| (notice.kind, notice.urgency))
+            .collect();
+        assert_eq!(
+            urgencies,
+            [
+                (NotificationKind::Connection, Urgency::Normal),
+                (NotificationKind::NewDevice, Urgency::Low),
+                (NotificationKind::KeyExpiry, Urgency::Critical),
+            ]
+        );
+    }
+
+    #[test]
+    fn do_not_disturb_lets_only_key_expiry_through() {
+        let recorder = Arc::new(RecordingNotifier::default());
+        recorder.set_do_not_disturb(true);
+        let notifications = notifications(&recorder);
+
+        notifications.connection_change(false);
+        notifications.incoming_files(2);
+        notifications.account_switched("work");
+        notifications.key_expiry(1);
+
+        let kinds: Vec<NotificationKind> = recorder
+            .shown()
+            .into_iter()
+            .map(|notice| notice.kind)
+            .collect();
+        assert_eq!(kinds, [NotificationKind::KeyExpiry]);
+    }
+
+    #[test]
+    fn key_expiry_reports_reauth() {
+        let recorder = Arc::new(RecordingNotifier::choosing("reauth"));
+        assert!(notifications(&recorder).key_expiry(0));
+
+        let recorder = Arc::new(RecordingNotifier::choosing("dismiss"));
+        assert!(!notifications(&recorder).key_expiry(0));
+
+        let recorder = Arc::new(RecordingNotifier::default());
+        assert!(!notifications(&recorder).key_expiry(0));
+    }
+
+    #[test]
+    fn styles_set_icon_and_sound() {
+        let recorder = Arc::new(RecordingNotifier::default());
+        let style = NotificationStyle {
+            icon: "network-vpn".to_string(),
+            sound: "message-new-instant".to_string(),
+        };
+        let notifications = Notifications::new(
+            recorder.clone(),
+            BTreeMap::from([(NotificationKind::Login, style)]),
+        );
+
+        notifications.login_succeeded();
+        notifications.connection_change(true);
+
+        let shown = recorder.shown();
+        assert_eq!(shown[0].icon, "network-vpn");
+        assert_eq!(shown[0].sound.as_deref(), Some("message-new-instant"));
+        assert_eq!(shown[1].icon, DEFAULT_ICON);
+        assert_eq!(shown[1].sound, None);
+    }
 }
@@ -0,0 +1,382 @@
+use crate::logic::DeviceInfo;
+use std::{
+    fmt::{self, Display, Formatter},
+    fs,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    str::FromStr,
+};
+
+/// An IPv4 or IPv6 network in CIDR notation, with the host bits cleared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+/// Why a subnet route was rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RouteError {
+    Empty,
+    MissingPrefix,
+    InvalidAddress(String),
+    InvalidPrefix(String),
+    /// `0.0.0.0/0` and `::/0` are advertised with the exit node toggle.
+    DefaultRoute,
+    Duplicate(Cidr),
+}
+
+impl Display for RouteError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RouteError::Empty => write!(f, "Enter a route"),
+            RouteError::MissingPrefix => write!(f, "Missing prefix length, e.g. /24"),
+            RouteError::InvalidAddress(addr) => write!(f, "Invalid IP address: {addr}"),
+            RouteError::InvalidPrefix(prefix) => write!(f, "Invalid prefix length: {prefix}"),
+            RouteError::DefaultRoute => {
+                write!(f, "Use \"Advertise as exit node\" for default routes")
+            }
+            RouteError::Duplicate(cidr) => write!(f, "{cidr} is already advertised"),
+        }
+    }
+}
+
+impl Cidr {
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    pub fn prefix(&self) -> u8 {
+        self.prefix
+    }
+
+    /// Build a CIDR from any address in the network, clearing host bits.
+    pub fn new(addr: IpAddr, prefix: u8) -> Option<Self> {
+        let max = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        (prefix <= max).then(|| Cidr {
+            addr: network(addr, prefix),
+            prefix,
+        })
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.prefix == 0
+    }
+
+    /// A single address: /32 or /128.
+    pub fn is_host(&self) -> bool {
+        match self.addr {
+            IpAddr::V4(_) => self.prefix == 32,
+            IpAddr::V6(_) => self.prefix == 128,
+        }
+    }
+
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        same_family(self.addr, addr) && network(addr, self.prefix) == self.addr
+    }
+
+    /// Do the two networks share any address.
+    pub fn overlaps(&self, other: &Cidr) -> bool {
+        let prefix = self.prefix.min(other.prefix);
+        same_family(self.addr, other.addr)
+            && network(self.addr, prefix) == network(other.addr, prefix)
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = RouteError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(RouteError::Empty);
+        }
+        let (addr, prefix) = s.split_once('/').ok_or(RouteError::MissingPrefix)?;
+        let addr: IpAddr = addr
+            .parse()
+            .map_err(|_| RouteError::InvalidAddress(addr.to_string()))?;
+        let prefix_err = || RouteError::InvalidPrefix(prefix.to_string());
+        let prefix: u8 = prefix.parse().map_err(|_| prefix_err())?;
+
+        Cidr::new(addr, prefix).ok_or_else(prefix_err)
+    }
+}
+
+impl Display for Cidr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+fn same_family(a: IpAddr, b: IpAddr) -> bool {
+    a.is_ipv4() == b.is_ipv4()
+}
+
+/// `addr` with every bit past `prefix` cleared.
+fn network(addr: IpAddr, prefix: u8) -> IpAddr {
+    match addr {
+        IpAddr::V4(v4) => {
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            IpAddr::V4(Ipv4Addr::from(u32::from(v4) & mask))
+        }
+        IpAddr::V6(v6) => {
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            IpAddr::V6(Ipv6Addr::from(u128::from(v6) & mask))
+        }
+    }
+}
+
+/// Parse and normalize a route to add, rejecting default routes and routes
+/// already in `existing`.
+pub fn validate_new_route(input: &str, existing: &[String]) -> Result<Cidr, RouteError> {
+    let cidr: Cidr = input.parse()?;
+    if cidr.is_default() {
+        return Err(RouteError::DefaultRoute);
+    }
+    if existing
+        .iter()
+        .any(|route| route.parse::<Cidr>().is_ok_and(|route| route == cidr))
+    {
+        return Err(RouteError::Duplicate(cidr));
+    }
+
+    Ok(cidr)
+}
+
+/// Something an advertised route overlaps with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RouteOverlap {
+    /// A network directly attached to one of this host's interfaces.
+    LocalInterface { iface: String, network: Cidr },
+    /// A route another peer is the primary router for.
+    Peer { name: String, route: Cidr },
+}
+
+impl Display for RouteOverlap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RouteOverlap::LocalInterface { iface, network } => {
+                write!(f, "Overlaps {network} on local interface {iface}")
+            }
+            RouteOverlap::Peer { name, route } => {
+                write!(f, "Overlaps {route} routed by {name}")
+            }
+        }
+    }
+}
+
+/// Local networks and peers' primary routes that `route` overlaps.
+pub fn route_overlaps(
+    route: &Cidr,
+    local_networks: &[(String, Cidr)],
+    devices: &[DeviceInfo],
+) -> Vec<RouteOverlap> {
+    let local = local_networks
+        .iter()
+        .filter(|(_, network)| network.overlaps(route))
+        .map(|(iface, network)| RouteOverlap::LocalInterface {
+            iface: iface.clone(),
+            network: *network,
+        });
+
+    let peers = devices.iter().filter(|dev| !dev.is_self).flat_map(|dev| {
+        dev.primary_routes
+            .iter()
+            .filter_map(|route| route.parse::<Cidr>().ok())
+            .filter(|peer_route| !peer_route.is_default() && peer_route.overlaps(route))
+            .map(|peer_route| RouteOverlap::Peer {
+                name: dev.name.clone(),
+                route: peer_route,
+            })
+    });
+
+    local.chain(peers).collect()
+}
+
+/// Whether this node currently uses a peer's subnet route.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeerRouteStatus {
+    /// Traffic for the route goes through this peer.
+    Active,
+    /// Another peer is the primary router for the route.
+    Standby,
+    /// "Accept routes" is off on this node.
+    NotAccepted,
+    Offline,
+}
+
+/// A subnet route advertised by a peer.
+#[derive(Debug, Clone)]
+pub struct PeerRoute {
+    pub route: Cidr,
+    pub status: PeerRouteStatus,
+    /// Local networks the route overlaps as `(interface, network)`. Using
+    /// the route would shadow part of the LAN.
+    pub lan_conflicts: Vec<(String, Cidr)>,
+}
+
+/// The subnet routes `dev` advertises and whether this node uses them.
+pub fn peer_routes(
+    dev: &DeviceInfo,
+    accept_routes: bool,
+    local_networks: &[(String, Cidr)],
+) -> Vec<PeerRoute> {
+    dev.subnet_routes
+        .iter()
+        .filter_map(|route| route.parse::<Cidr>().ok())
+        .map(|route| {
+            let primary = dev
+                .primary_routes
+                .iter()
+                .any(|primary| primary.parse::<Cidr>() == Ok(route));
+            let status = if !dev.online {
+                PeerRouteStatus::Offline
+            } else if !primary {
+                PeerRouteStatus::Standby
+            } else if !accept_routes {
+                PeerRouteStatus::NotAccepted
+            } else {
+                PeerRouteStatus::Active
+            };
+
+            PeerRoute {
+                route,
+                status,
+                lan_conflicts: local_networks
+                    .iter()
+                    .filter(|(_, network)| network.overlaps(&route))
+                    .cloned()
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+/// On-link networks of this host's interfaces as `(interface, network)`,
+/// read from the kernel routing tables. Loopback, Tailscale's own interface,
+/// default and host routes and IPv6 link-local/multicast networks are
+/// skipped.
+pub fn local_networks() -> Vec<(String, Cidr)> {
+    let mut networks = Vec::new();
+
+    // Iface Destination Gateway Flags RefCnt Use Metric Mask ...
+    // Addresses are host byte order hex.
+    if let Ok(table) = fs::read_to_string("/proc/net/route") {
+        for line in table.lines().skip(1) {
+            let cols: Vec<&str> = line.split_whitespace().collect();
+            if cols.len() < 8 || cols[2] != "00000000" {
+                continue;
+            }
+            let hex = |col: &str| u32::from_str_radix(col, 16).ok();
+            if let (Some(dest), Some(mask)) = (hex(cols[1]), hex(cols[7]))
+                && let Some(cidr) = Cidr::new(
+                    IpAddr::V4(Ipv4Addr::from(dest.to_ne_bytes())),
+                    mask.count_ones() as u8,
+                )
+            {
+                networks.push((cols[0].to_string(), cidr));
+            }
+        }
+    }
+
+    // Destination PrefixLen Source SrcPrefixLen NextHop Metric RefCnt Use
+    // Flags Iface. Addresses are network byte order hex.
+    if let Ok(table) = fs::read_to_string("/proc/net/ipv6_route") {
+        for line in table.lines() {
+            let cols: Vec<&str> = line.split_whitespace().collect();
+            if cols.len() < 10 || cols[4].bytes().any(|b| b != b'0') {
+                continue;
+            }
+            if let Ok(dest) = u128::from_str_radix(cols[0], 16)
+                && let Ok(prefix) = u8::from_str_radix(cols[1], 16)
+                && let Some(cidr) = Cidr::new(IpAddr::V6(Ipv6Addr::from(dest)), prefix)
+                && !matches!(cidr.addr(), IpAddr::V6(v6) if v6.is_unicast_link_local() || v6.is_multicast())
+            {
+                networks.push((cols[9].to_string(), cidr));
+            }
+        }
+    }
+
+    networks.retain(|(iface, cidr)| {
+        iface != "lo" && !iface.starts_with("tailscale") && !cidr.is_default() && !cidr.is_host()
+    });
+    networks.sort_by_key(|(iface, cidr)| (iface.clone(), cidr.to_string()));
+    networks.dedup();
+    networks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cidr(s: &str) -> Cidr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_clears_host_bits() {
+        assert_eq!(cidr("10.0.0.5/24").to_string(), "10.0.0.0/24");
+        assert_eq!(cidr(" 192.168.1.77/32 ").to_string(), "192.168.1.77/32");
+        assert_eq!(
+            cidr("fd7a:115c:a1e0::1/48").to_string(),
+            "fd7a:115c:a1e0::/48"
+        );
+        assert!(cidr("0.0.0.0/0").is_default());
+        assert!(cidr("::1/128").is_host());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!("  ".parse::<Cidr>(), Err(RouteError::Empty));
+        assert_eq!("10.0.0.0".parse::<Cidr>(), Err(RouteError::MissingPrefix));
+        assert_eq!(
+            "10.0.0/8".parse::<Cidr>(),
+            Err(RouteError::InvalidAddress("10.0.0".to_string()))
+        );
+        assert_eq!(
+            "10.0.0.0/33".parse::<Cidr>(),
+            Err(RouteError::InvalidPrefix("33".to_string()))
+        );
+        assert_eq!(
+            "::/129".parse::<Cidr>(),
+            Err(RouteError::InvalidPrefix("129".to_string()))
+        );
+        assert_eq!(
+            "10.0.0.0/x".parse::<Cidr>(),
+            Err(RouteError::InvalidPrefix("x".to_string()))
+        );
+    }
+
+    #[test]
+    fn contains_and_overlaps() {
+        let lan = cidr("192.168.0.0/16");
+        assert!(lan.contains("192.168.4.2".parse().unwrap()));
+        assert!(!lan.contains("192.169.0.1".parse().unwrap()));
+        assert!(!lan.contains("::ffff:192.168.4.2".parse().unwrap()));
+
+        assert!(lan.overlaps(&cidr("192.168.4.0/24")));
+        assert!(cidr("192.168.4.0/24").overlaps(&lan));
+        assert!(!lan.overlaps(&cidr("10.0.0.0/8")));
+        assert!(cidr("fd00::/8").overlaps(&cidr("fd12::/16")));
+        assert!(!cidr("0.0.0.0/0").overlaps(&cidr("::/0")));
+    }
+
+    #[test]
+    fn new_routes_are_validated() {
+        let existing = vec!["10.0.0.0/24".to_string()];
+        assert_eq!(
+            validate_new_route("10.1.0.9/16", &existing),
+            Ok(cidr("10.1.0.0/16"))
+        );
+        assert_eq!(
+            validate_new_route("0.0.0.0/0", &existing),
+            Err(RouteError::DefaultRoute)
+        );
+        assert_eq!(
+            validate_new_route("10.0.0.7/24", &existing),
+            Err(RouteError::Duplicate(cidr("10.0.0.0/24")))
+        );
+    }
+}
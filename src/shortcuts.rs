@@ -0,0 +1,164 @@
+use crate::dbus::{DBUS_NAME, DBUS_PATH};
+use cosmic::cosmic_config::{self, Config, ConfigGet, ConfigSet};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+};
+
+/// cosmic-settings' shortcut config; cosmic-comp reads custom key bindings
+/// from its `custom` key.
+const SHORTCUTS_ID: &str = "com.system76.CosmicSettings.Shortcuts";
+const SHORTCUTS_VERS: u64 = 1;
+const CUSTOM_KEY: &str = "custom";
+
+/// A key combination, in cosmic-settings-config's `Binding` layout.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+struct Binding {
+    #[serde(default)]
+    modifiers: Vec<Modifier>,
+    /// xkb keysym name, e.g. "t".
+    key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+enum Modifier {
+    Super,
+    Ctrl,
+    Alt,
+    Shift,
+}
+
+/// The shortcut actions the applet uses. Custom shortcuts that rebind other
+/// compositor actions can't be represented, so the config is left alone
+/// when it holds any ([`ShortcutError::UnsupportedBindings`]).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+enum Action {
+    Disable,
+    Spawn(String),
+}
+
+/// The applet's global shortcuts: `(modifiers, key, description, D-Bus
+/// method)`. The compositor runs them as commands calling the applet's
+/// session D-Bus interface.
+const SHORTCUTS: [(&[Modifier], &str, &str, &str); 2] = [
+    (
+        &[Modifier::Super, Modifier::Alt],
+        "t",
+        "Tailscale: toggle connection",
+        "ToggleConnection",
+    ),
+    (
+        &[Modifier::Super, Modifier::Alt],
+        "p",
+        "Tailscale: next profile",
+        "NextProfile",
+    ),
+];
+
+/// Why the applet's shortcuts couldn't be changed.
+#[derive(Debug)]
+pub enum ShortcutError {
+    /// The user already bound one of the applet's keys, e.g. "Super+Alt+T".
+    Conflict(String),
+    /// The custom shortcuts include actions other than commands, which the
+    /// applet can't write back without losing them.
+    UnsupportedBindings,
+    Config(cosmic_config::Error),
+}
+
+impl From<cosmic_config::Error> for ShortcutError {
+    fn from(err: cosmic_config::Error) -> Self {
+        ShortcutError::Config(err)
+    }
+}
+
+impl Display for ShortcutError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ShortcutError::Conflict(keys) => {
+                write!(f, "{keys} is already used by another custom shortcut")
+            }
+            ShortcutError::UnsupportedBindings => write!(
+                f,
+                "Custom shortcuts use actions the applet can't edit; add these in Settings instead"
+            ),
+            ShortcutError::Config(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+fn command(method: &str) -> String {
+    format!("busctl --user call {DBUS_NAME} {DBUS_PATH} {DBUS_NAME}1 {method}")
+}
+
+/// Shortcut keys as shown to the user, e.g. "Super+Alt+T".
+fn label(modifiers: &[Modifier], key: &str) -> String {
+    let mut keys: Vec<String> = modifiers.iter().map(|m| format!("{m:?}")).collect();
+    keys.push(key.to_uppercase());
+    keys.join("+")
+}
+
+/// Whether `binding` is triggered by `modifiers` + `key`.
+fn same_keys(binding: &Binding, modifiers: &[Modifier], key: &str) -> bool {
+    binding
+        .key
+        .as_deref()
+        .is_some_and(|k| k.eq_ignore_ascii_case(key))
+        && binding.modifiers.len() == modifiers.len()
+        && modifiers.iter().all(|m| binding.modifiers.contains(m))
+}
+
+/// Add the applet's shortcuts to COSMIC's custom shortcuts, or remove them.
+/// Adding is refused if the user has bound any of the same keys.
+pub fn set_global_shortcuts(enabled: bool) -> Result<(), ShortcutError> {
+    let config = Config::new(SHORTCUTS_ID, SHORTCUTS_VERS)?;
+    let mut custom: HashMap<Binding, Action> = match config.get(CUSTOM_KEY) {
+        Ok(custom) => custom,
+        // No custom shortcuts yet.
+        Err(err) if !err.is_err() => HashMap::new(),
+        Err(cosmic_config::Error::RonSpanned(_)) => {
+            return Err(ShortcutError::UnsupportedBindings);
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    let ours: Vec<String> = SHORTCUTS
+        .iter()
+        .map(|(_, _, _, method)| command(method))
+        .collect();
+    custom.retain(|_, action| !matches!(action, Action::Spawn(cmd) if ours.contains(cmd)));
+
+    if enabled {
+        if let Some((modifiers, key, _, _)) = SHORTCUTS.iter().find(|(modifiers, key, _, _)| {
+            custom
+                .keys()
+                .any(|binding| same_keys(binding, modifiers, key))
+        }) {
+            return Err(ShortcutError::Conflict(label(modifiers, key)));
+        }
+
+        for (modifiers, key, description, method) in SHORTCUTS {
+            custom.insert(
+                Binding {
+                    modifiers: modifiers.to_vec(),
+                    key: Some(key.to_string()),
+                    description: Some(description.to_string()),
+                },
+                Action::Spawn(command(method)),
+            );
+        }
+    }
+
+    Ok(config.set(CUSTOM_KEY, custom)?)
+}
+
+/// The shortcuts as shown in Settings, e.g. "Super+Alt+T".
+pub fn shortcut_labels() -> Vec<(String, &'static str)> {
+    SHORTCUTS
+        .iter()
+        .map(|(modifiers, key, description, _)| (label(modifiers, key), *description))
+        .collect()
+}
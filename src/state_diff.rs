@@ -0,0 +1,433 @@
+use crate::{
+    logic::{DeviceInfo, TailscaleState},
+    network::ExitNodeRule,
+};
+
+/// A daemon preference that changed between two snapshots, with its new value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrefChange {
+    Ssh(bool),
+    AcceptRoutes(bool),
+    MagicDns(bool),
+    AdvertiseExitNode(bool),
+    ExitNodeAllowLan(bool),
+    AdvertisedRoutes(Vec<String>),
+    AdvertiseTags(Vec<String>),
+    AppConnector(bool),
+}
+
+/// One change between two `TailscaleState` snapshots. Devices are identified
+/// by node ID and carry their display name for notifications.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateChange {
+    Connected,
+    Disconnected,
+    DeviceAdded {
+        id: String,
+        name: String,
+    },
+    DeviceRemoved {
+        id: String,
+        name: String,
+    },
+    DeviceOnline {
+        id: String,
+        name: String,
+    },
+    DeviceOffline {
+        id: String,
+        name: String,
+    },
+    /// A peer's relay or direct connection changed while its online state
+    /// didn't.
+    DeviceConnectionChanged {
+        id: String,
+        name: String,
+    },
+    /// Exit node in use changed; `None` means no exit node.
+    ExitNodeChanged {
+        from: Option<String>,
+        to: Option<String>,
+    },
+    AccountSwitched {
+        from: String,
+        to: String,
+    },
+    PrefChanged(PrefChange),
+    /// Files that were not waiting in the TailDrop inbox before.
+    FilesArrived(Vec<String>),
+}
+
+/// A daemon preference to set back to the wanted value, found by [`drift`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Correction {
+    Ssh(bool),
+    AcceptRoutes(bool),
+    MagicDns(bool),
+    ExitNodeAllowLan(bool),
+    /// Exit node to switch to; `None` means no exit node.
+    ExitNode(Option<String>),
+}
+
+/// The daemon preferences the user asked to keep.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnforcedPrefs {
+    pub ssh: bool,
+    pub accept_routes: bool,
+    pub magic_dns: bool,
+    pub exit_node_allow_lan: bool,
+    pub exit_node: ExitNodeRule,
+}
+
+/// Everything that changed from one `TailscaleState` snapshot to the next.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateDiff {
+    pub changes: Vec<StateChange>,
+}
+
+impl StateDiff {
+    /// Compute the changes from `old` to `new`.
+    pub fn between(old: &TailscaleState, new: &TailscaleState) -> Self {
+        let mut changes = Vec::new();
+
+        if old.connected != new.connected {
+            changes.push(if new.connected {
+                StateChange::Connected
+            } else {
+                StateChange::Disconnected
+            });
+        }
+
+        let old_account = current_account(old);
+        let new_account = current_account(new);
+        let mut account_switched = false;
+        if let (Some(from), Some(to)) = (old_account, new_account)
+            && from.0 != to.0
+        {
+            account_switched = true;
+            changes.push(StateChange::AccountSwitched {
+                from: from.1.to_string(),
+                to: to.1.to_string(),
+            });
+        }
+
+        // Every peer would look added or removed across an account switch.
+        let old_peers = || peers(old).filter(|_| !account_switched);
+        let new_peers = || peers(new).filter(|_| !account_switched);
+
+        for dev in new_peers() {
+            match old_peers().find(|old_dev| old_dev.id == dev.id) {
+                None => changes.push(StateChange::DeviceAdded {
+                    id: dev.id.clone(),
+                    name: dev.name.clone(),
+                }),
+                Some(old_dev) if old_dev.online != dev.online => {
+                    changes.push(if dev.online {
+                        StateChange::DeviceOnline {
+                            id: dev.id.clone(),
+                            name: dev.name.clone(),
+                        }
+                    } else {
+                        StateChange::DeviceOffline {
+                            id: dev.id.clone(),
+                            name: dev.name.clone(),
+                        }
+                    });
+                }
+                Some(old_dev) if old_dev.relay != dev.relay || old_dev.direct != dev.direct => {
+                    changes.push(StateChange::DeviceConnectionChanged {
+                        id: dev.id.clone(),
+                        name: dev.name.clone(),
+                    });
+                }
+                Some(_) => {}
+            }
+        }
+        for dev in old_peers() {
+            if !new_peers().any(|new_dev| new_dev.id == dev.id) {
+                changes.push(StateChange::DeviceRemoved {
+                    id: dev.id.clone(),
+                    name: dev.name.clone(),
+                });
+            }
+        }
+
+        let old_exit = exit_node_name(old);
+        let new_exit = exit_node_name(new);
+        if old_exit != new_exit {
+            changes.push(StateChange::ExitNodeChanged {
+                from: old_exit,
+                to: new_exit,
+            });
+        }
+
+        let prefs = [
+            (old.ssh_enabled != new.ssh_enabled).then_some(PrefChange::Ssh(new.ssh_enabled)),
+            (old.accept_routes != new.accept_routes)
+                .then_some(PrefChange::AcceptRoutes(new.accept_routes)),
+            (old.magic_dns != new.magic_dns).then_some(PrefChange::MagicDns(new.magic_dns)),
+            (old.is_exit_node != new.is_exit_node)
+                .then_some(PrefChange::AdvertiseExitNode(new.is_exit_node)),
+            (old.exit_node_allow_lan != new.exit_node_allow_lan)
+                .then_some(PrefChange::ExitNodeAllowLan(new.exit_node_allow_lan)),
+            (old.advertised_routes != new.advertised_routes)
+                .then(|| PrefChange::AdvertisedRoutes(new.advertised_routes.clone())),
+            (old.advertised_tags != new.advertised_tags)
+                .then(|| PrefChange::AdvertiseTags(new.advertised_tags.clone())),
+            (old.app_connector != new.app_connector)
+                .then_some(PrefChange::AppConnector(new.app_connector)),
+        ];
+        changes.extend(prefs.into_iter().flatten().map(StateChange::PrefChanged));
+
+        let arrived: Vec<String> = new
+            .waiting_files
+            .iter()
+            .filter(|file| !old.waiting_files.iter().any(|old| old.name == file.name))
+            .map(|file| file.name.clone())
+            .collect();
+        if !arrived.is_empty() {
+            changes.push(StateChange::FilesArrived(arrived));
+        }
+
+        StateDiff { changes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Did the current account change. Device changes are left out then,
+    /// since every peer would appear added or removed.
+    pub fn account_switched(&self) -> bool {
+        self.changes
+            .iter()
+            .any(|change| matches!(change, StateChange::AccountSwitched { .. }))
+    }
+}
+
+/// The corrections that bring `state` back to `wanted`.
+pub fn drift(state: &TailscaleState, wanted: &EnforcedPrefs) -> Vec<Correction> {
+    let mut corrections: Vec<Correction> = [
+        (state.ssh_enabled != wanted.ssh).then_some(Correction::Ssh(wanted.ssh)),
+        (state.accept_routes != wanted.accept_routes)
+            .then_some(Correction::AcceptRoutes(wanted.accept_routes)),
+        (state.magic_dns != wanted.magic_dns).then_some(Correction::MagicDns(wanted.magic_dns)),
+        (state.exit_node_allow_lan != wanted.exit_node_allow_lan)
+            .then_some(Correction::ExitNodeAllowLan(wanted.exit_node_allow_lan)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+
+    let current = exit_node_name(state);
+    let exit_node = match &wanted.exit_node {
+        ExitNodeRule::Keep => None,
+        ExitNodeRule::Clear => current.is_some().then_some(None),
+        ExitNodeRule::Use(name) => (current.as_ref() != Some(name)).then(|| Some(name.clone())),
+    };
+    corrections.extend(exit_node.map(Correction::ExitNode));
+
+    corrections
+}
+
+fn peers(state: &TailscaleState) -> impl Iterator<Item = &DeviceInfo> {
+    state.devices.iter().filter(|dev| !dev.is_self)
+}
+
+/// `(id, name)` of the current account.
+fn current_account(state: &TailscaleState) -> Option<(&str, &str)> {
+    state
+        .accounts
+        .iter()
+        .find(|acct| acct.is_current)
+        .map(|acct| (acct.id.as_str(), acct.name.as_str()))
+}
+
+fn exit_node_name(state: &TailscaleState) -> Option<String> {
+    peers(state)
+        .find(|dev| dev.is_exit_node)
+        .map(|dev| dev.name.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{logic::AccountInfo, tailscale_api::WaitingFile};
+
+    fn peer(id: &str, online: bool) -> DeviceInfo {
+        DeviceInfo {
+            id: id.to_string(),
+            name: format!("{id}-name"),
+            online,
+            ..DeviceInfo::default()
+        }
+    }
+
+    fn state(devices: Vec<DeviceInfo>) -> TailscaleState {
+        TailscaleState {
+            connected: true,
+            devices,
+            accounts: vec![account("profile-a", true)],
+            ..TailscaleState::default()
+        }
+    }
+
+    fn account(id: &str, is_current: bool) -> AccountInfo {
+        AccountInfo {
+            id: id.to_string(),
+            name: format!("{id}-name"),
+            is_current,
+            ..AccountInfo::default()
+        }
+    }
+
+    #[test]
+    fn unchanged_state_is_empty() {
+        let old = state(vec![peer("a", true)]);
+        assert!(StateDiff::between(&old, &old.clone()).is_empty());
+    }
+
+    #[test]
+    fn devices_added_and_removed() {
+        let old = state(vec![peer("a", true), peer("b", true)]);
+        let new = state(vec![peer("a", true), peer("c", true)]);
+
+        let diff = StateDiff::between(&old, &new);
+        assert_eq!(
+            diff.changes,
+            vec![
+                StateChange::DeviceAdded {
+                    id: "c".to_string(),
+                    name: "c-name".to_string(),
+                },
+                StateChange::DeviceRemoved {
+                    id: "b".to_string(),
+                    name: "b-name".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn devices_online_and_offline() {
+        let old = state(vec![peer("a", false), peer("b", true)]);
+        let new = state(vec![peer("a", true), peer("b", false)]);
+
+        let diff = StateDiff::between(&old, &new);
+        assert_eq!(
+            diff.changes,
+            vec![
+                StateChange::DeviceOnline {
+                    id: "a".to_string(),
+                    name: "a-name".to_string(),
+                },
+                StateChange::DeviceOffline {
+                    id: "b".to_string(),
+                    name: "b-name".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn this_device_is_not_a_peer() {
+        let mut this = peer("self", true);
+        this.is_self = true;
+        let old = state(vec![]);
+        let new = state(vec![this]);
+
+        assert!(StateDiff::between(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn exit_node_change() {
+        let mut exit = peer("b", true);
+        exit.is_exit_node = true;
+        let old = state(vec![peer("a", true), peer("b", true)]);
+        let new = state(vec![peer("a", true), exit]);
+
+        assert_eq!(
+            StateDiff::between(&old, &new).changes,
+            vec![StateChange::ExitNodeChanged {
+                from: None,
+                to: Some("b-name".to_string()),
+            }]
+        );
+        assert_eq!(
+            StateDiff::between(&new, &old).changes,
+            vec![StateChange::ExitNodeChanged {
+                from: Some("b-name".to_string()),
+                to: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn account_switch_suppresses_device_churn() {
+        let old = state(vec![peer("a", true), peer("b", true)]);
+        let mut new = state(vec![peer("c", true)]);
+        new.accounts = vec![account("profile-a", false), account("profile-b", true)];
+
+        let diff = StateDiff::between(&old, &new);
+        assert!(diff.account_switched());
+        assert_eq!(
+            diff.changes,
+            vec![StateChange::AccountSwitched {
+                from: "profile-a-name".to_string(),
+                to: "profile-b-name".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn files_arrived_lists_only_new_files() {
+        let file = |name: &str| WaitingFile {
+            name: name.to_string(),
+            size: 1,
+        };
+        let mut old = state(vec![]);
+        old.waiting_files = vec![file("old.txt")];
+        let mut new = old.clone();
+        new.waiting_files = vec![file("old.txt"), file("new.txt")];
+
+        assert_eq!(
+            StateDiff::between(&old, &new).changes,
+            vec![StateChange::FilesArrived(vec!["new.txt".to_string()])]
+        );
+        assert!(StateDiff::between(&new, &old).is_empty());
+    }
+
+    #[test]
+    fn drift_corrects_only_what_differs() {
+        let mut exit = peer("b", true);
+        exit.is_exit_node = true;
+        let mut current = state(vec![peer("a", true), exit]);
+        current.ssh_enabled = true;
+        current.magic_dns = true;
+        let mut wanted = EnforcedPrefs {
+            ssh: true,
+            accept_routes: true,
+            magic_dns: true,
+            exit_node_allow_lan: false,
+            exit_node: ExitNodeRule::Keep,
+        };
+
+        assert_eq!(
+            drift(&current, &wanted),
+            vec![Correction::AcceptRoutes(true)]
+        );
+
+        wanted.accept_routes = false;
+        wanted.exit_node = ExitNodeRule::Use("b-name".to_string());
+        assert!(drift(&current, &wanted).is_empty());
+
+        wanted.exit_node = ExitNodeRule::Use("a-name".to_string());
+        assert_eq!(
+            drift(&current, &wanted),
+            vec![Correction::ExitNode(Some("a-name".to_string()))]
+        );
+
+        wanted.exit_node = ExitNodeRule::Clear;
+        assert_eq!(drift(&current, &wanted), vec![Correction::ExitNode(None)]);
+    }
+}
@@ -5,13 +5,12 @@ use hyper::{
     client::conn::http1::{SendRequest, handshake},
 };
 use hyper_util::rt::TokioIo;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, de::IgnoredAny};
 use std::{
     collections::HashMap,
     fmt::{self, Display, Formatter},
     io::ErrorKind,
     path::Path,
-    time::Duration,
 };
 use tokio::net::UnixStream;
 
@@ -50,7 +49,7 @@ impl Display for TailscaleError {
             }
             TailscaleError::RequestFailed(err) => write!(f, "Request failed: {err}"),
             TailscaleError::ParseError(err) => write!(f, "Parse error: {err}"),
-            TailscaleError::ApiError(code, body) => write!(f, "API error (HTTP {code}: {body}"),
+            TailscaleError::ApiError(code, body) => write!(f, "API error (HTTP {code}): {body}"),
             TailscaleError::OperatorNotSet => write!(
                 f,
                 "Tailscale operator not set for your user.\n\
@@ -62,6 +61,68 @@ impl Display for TailscaleError {
 
 pub type TsResult<T> = Result<T, TailscaleError>;
 
+/// Why logging in with an auth key failed.
+#[derive(Debug, Clone)]
+pub enum AuthKeyError {
+    Empty,
+    /// The control server doesn't recognise the key. Its format is up to
+    /// the server: Tailscale's start with `tskey-`, Headscale's don't.
+    Malformed,
+    Expired,
+    /// A single-use key that has already registered a device.
+    AlreadyUsed,
+    /// The control server rejected the key for another reason.
+    Rejected(String),
+    /// The daemon couldn't be reached or refused the request.
+    Daemon(TailscaleError),
+}
+
+impl AuthKeyError {
+    /// Classify an error message from the control server.
+    pub fn from_message(message: &str) -> Self {
+        let lower = message.to_lowercase();
+        if lower.contains("expired") {
+            AuthKeyError::Expired
+        } else if ["invalid key", "invalid authkey", "authkey not found"]
+            .iter()
+            .any(|phrase| lower.contains(phrase))
+        {
+            AuthKeyError::Malformed
+        } else if lower.contains("already used") || lower.contains("already been used") {
+            AuthKeyError::AlreadyUsed
+        } else {
+            AuthKeyError::Rejected(message.trim().to_string())
+        }
+    }
+}
+
+impl From<TailscaleError> for AuthKeyError {
+    fn from(err: TailscaleError) -> Self {
+        match err {
+            // Key problems come back as the daemon's error text.
+            TailscaleError::ApiError(_, body) => AuthKeyError::from_message(&body),
+            err => AuthKeyError::Daemon(err),
+        }
+    }
+}
+
+impl Display for AuthKeyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthKeyError::Empty => write!(f, "Enter an auth key"),
+            AuthKeyError::Malformed => {
+                write!(f, "The control server doesn't recognise this auth key")
+            }
+            AuthKeyError::Expired => write!(f, "The auth key has expired"),
+            AuthKeyError::AlreadyUsed => {
+                write!(f, "The auth key is single-use and has already been used")
+            }
+            AuthKeyError::Rejected(msg) => write!(f, "The auth key was rejected: {msg}"),
+            AuthKeyError::Daemon(err) => write!(f, "{err}"),
+        }
+    }
+}
+
 /// Full status response from `/localapi/v0/status`.
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "PascalCase")]
@@ -84,6 +145,24 @@ pub struct Status {
     /// MagicDNS suffix for the tailnet.
     #[serde(default)]
     pub magic_dns_suffix: String,
+    /// Map of user ID -> profile of the users owning nodes.
+    #[serde(default)]
+    pub user: HashMap<String, UserProfile>,
+}
+
+/// A user owning one or more nodes.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct UserProfile {
+    /// User ID.
+    #[serde(rename = "ID", default)]
+    pub id: i64,
+    /// Login name, e.g. an email address.
+    #[serde(default)]
+    pub login_name: String,
+    /// Display name.
+    #[serde(default)]
+    pub display_name: String,
 }
 
 /// Status of peer or self node.
@@ -123,6 +202,9 @@ pub struct PeerStatus {
     /// DERP relay region.
     #[serde(default)]
     pub relay: String,
+    /// Current direct endpoint; empty while traffic is relayed.
+    #[serde(default)]
+    pub cur_addr: String,
     /// Bytes received from this peer.
     #[serde(default)]
     pub rx_bytes: u64,
@@ -138,6 +220,27 @@ pub struct PeerStatus {
     /// Is a Mullvad exit node.
     #[serde(default)]
     pub is_mullvad: bool,
+    /// When the node key expires (RFC 3339). Absent when expiry is disabled.
+    #[serde(default)]
+    pub key_expiry: Option<String>,
+    /// Has the node key already expired.
+    #[serde(default)]
+    pub expired: bool,
+    /// ID of the user owning the node; see [`Status::user`].
+    #[serde(rename = "UserID", default)]
+    pub user_id: i64,
+    /// The node was shared into this tailnet from another one.
+    #[serde(default)]
+    pub sharee_node: bool,
+    /// SSH host keys; present when the peer runs Tailscale SSH.
+    #[serde(rename = "sshHostKeys", default)]
+    pub ssh_host_keys: Option<Vec<String>>,
+    /// Subnet routes this node is the active router for.
+    #[serde(default)]
+    pub primary_routes: Option<Vec<String>>,
+    /// Addresses routed to this node: its Tailscale IPs plus approved routes.
+    #[serde(rename = "AllowedIPs", default)]
+    pub allowed_ips: Option<Vec<String>>,
 }
 
 /// Telnet Info
@@ -186,15 +289,128 @@ pub struct Prefs {
     /// Operator user.
     #[serde(default)]
     pub operator_user: String,
+    /// Tags this node requests from the control plane.
+    #[serde(default)]
+    pub advertise_tags: Option<Vec<String>>,
+    /// App connector settings.
+    #[serde(default)]
+    pub app_connector: AppConnectorPrefs,
+    /// Block all incoming connections.
+    #[serde(default)]
+    pub shields_up: bool,
+    /// Don't source-NAT traffic to advertised subnet routes.
+    #[serde(rename = "NoSNAT", default)]
+    pub no_snat: bool,
+    /// How much tailscaled manages netfilter rules.
+    #[serde(default)]
+    pub netfilter_mode: NetfilterMode,
+    /// Report device posture to the control plane.
+    #[serde(default)]
+    pub posture_checking: bool,
+    /// Automatic update settings.
+    #[serde(default)]
+    pub auto_update: AutoUpdatePrefs,
+    /// Coordination server URL; empty means the default.
+    #[serde(rename = "ControlURL", default)]
+    pub control_url: String,
+    /// The user explicitly logged out.
+    #[serde(default)]
+    pub logged_out: bool,
+    /// Display name of the current profile.
+    #[serde(default)]
+    pub profile_name: String,
+    /// Serve the web client on port 5252.
+    #[serde(default)]
+    pub run_web_client: bool,
+}
+
+/// How much tailscaled manages netfilter (iptables/nftables) rules. Sent as
+/// the integer values of Tailscale's `preftype.NetfilterMode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(from = "u8", into = "u8")]
+pub enum NetfilterMode {
+    /// Don't touch netfilter at all.
+    Off,
+    /// Add rules but don't divert traffic into them.
+    NoDivert,
+    /// Fully manage netfilter rules.
+    #[default]
+    On,
+}
+
+impl NetfilterMode {
+    pub const ALL: [NetfilterMode; 3] = [
+        NetfilterMode::Off,
+        NetfilterMode::NoDivert,
+        NetfilterMode::On,
+    ];
+}
+
+impl From<u8> for NetfilterMode {
+    fn from(mode: u8) -> Self {
+        match mode {
+            0 => NetfilterMode::Off,
+            1 => NetfilterMode::NoDivert,
+            _ => NetfilterMode::On,
+        }
+    }
+}
+
+impl From<NetfilterMode> for u8 {
+    fn from(mode: NetfilterMode) -> Self {
+        match mode {
+            NetfilterMode::Off => 0,
+            NetfilterMode::NoDivert => 1,
+            NetfilterMode::On => 2,
+        }
+    }
+}
+
+/// Automatic update settings within [`Prefs`].
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct AutoUpdatePrefs {
+    /// Periodically check for updates.
+    #[serde(default)]
+    pub check: bool,
+    /// Install updates automatically; `None` leaves it to the platform
+    /// default.
+    #[serde(default)]
+    pub apply: Option<bool>,
+}
+
+/// Mask for [`AutoUpdatePrefs`]; each field is masked separately.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct AutoUpdatePrefsMask {
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub check_set: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub apply_set: bool,
+}
+
+impl AutoUpdatePrefsMask {
+    fn is_empty(&self) -> bool {
+        !self.check_set && !self.apply_set
+    }
+}
+
+/// App connector settings within [`Prefs`].
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct AppConnectorPrefs {
+    /// Run as an app connector for the domains configured in the ACL.
+    #[serde(default)]
+    pub advertise: bool,
 }
 
 /// Partial prefs update for PATCH /localapi/v0/prefs.
 ///
 /// Tailscale's `MaskedPrefs` requires a `<Field>Set: true` flag for every
 /// field being changed; the daemon silently ignores fields whose mask is
-/// unset. The `*_set` booleans here are those mask flags. Use
-/// [`PrefsUpdate::set_*`] helpers (or set both fields manually) — setting a
-/// value without its mask is a no-op on the daemon.
+/// unset. The `*_set` booleans here are those mask flags. Build updates from
+/// a [`PrefEdit`] (or set both fields manually) — setting a value without
+/// its mask is a no-op on the daemon.
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "PascalCase")]
 pub struct PrefsUpdate {
@@ -244,6 +460,140 @@ pub struct PrefsUpdate {
     pub hostname: Option<String>,
     #[serde(default, skip_serializing_if = "is_false")]
     pub hostname_set: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub advertise_tags: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub advertise_tags_set: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app_connector: Option<AppConnectorPrefs>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub app_connector_set: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shields_up: Option<bool>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub shields_up_set: bool,
+
+    #[serde(rename = "NoSNAT", skip_serializing_if = "Option::is_none")]
+    pub no_snat: Option<bool>,
+    #[serde(rename = "NoSNATSet", default, skip_serializing_if = "is_false")]
+    pub no_snat_set: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub netfilter_mode: Option<NetfilterMode>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub netfilter_mode_set: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operator_user: Option<String>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub operator_user_set: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub posture_checking: Option<bool>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub posture_checking_set: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_update: Option<AutoUpdatePrefs>,
+    #[serde(default, skip_serializing_if = "AutoUpdatePrefsMask::is_empty")]
+    pub auto_update_set: AutoUpdatePrefsMask,
+
+    #[serde(rename = "ControlURL", skip_serializing_if = "Option::is_none")]
+    pub control_url: Option<String>,
+    #[serde(rename = "ControlURLSet", default, skip_serializing_if = "is_false")]
+    pub control_url_set: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logged_out: Option<bool>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub logged_out_set: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile_name: Option<String>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub profile_name_set: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run_web_client: Option<bool>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub run_web_client_set: bool,
+}
+
+/// A single typed preference change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrefEdit {
+    ShieldsUp(bool),
+    NoSnat(bool),
+    NetfilterMode(NetfilterMode),
+    OperatorUser(String),
+    PostureChecking(bool),
+    /// `AutoUpdate.Check`; the current `Apply` is kept.
+    AutoUpdateCheck(bool),
+    /// `AutoUpdate.Apply`; the current `Check` is kept.
+    AutoUpdateApply(bool),
+    ControlUrl(String),
+    LoggedOut(bool),
+    ProfileName(String),
+    RunWebClient(bool),
+}
+
+impl From<PrefEdit> for PrefsUpdate {
+    fn from(edit: PrefEdit) -> Self {
+        let mut update = PrefsUpdate::default();
+        match edit {
+            PrefEdit::ShieldsUp(val) => {
+                update.shields_up = Some(val);
+                update.shields_up_set = true;
+            }
+            PrefEdit::NoSnat(val) => {
+                update.no_snat = Some(val);
+                update.no_snat_set = true;
+            }
+            PrefEdit::NetfilterMode(mode) => {
+                update.netfilter_mode = Some(mode);
+                update.netfilter_mode_set = true;
+            }
+            PrefEdit::OperatorUser(user) => {
+                update.operator_user = Some(user);
+                update.operator_user_set = true;
+            }
+            PrefEdit::PostureChecking(val) => {
+                update.posture_checking = Some(val);
+                update.posture_checking_set = true;
+            }
+            PrefEdit::AutoUpdateCheck(check) => {
+                update.auto_update = Some(AutoUpdatePrefs { check, apply: None });
+                update.auto_update_set.check_set = true;
+            }
+            PrefEdit::AutoUpdateApply(apply) => {
+                update.auto_update = Some(AutoUpdatePrefs {
+                    check: false,
+                    apply: Some(apply),
+                });
+                update.auto_update_set.apply_set = true;
+            }
+            PrefEdit::ControlUrl(url) => {
+                update.control_url = Some(url);
+                update.control_url_set = true;
+            }
+            PrefEdit::LoggedOut(val) => {
+                update.logged_out = Some(val);
+                update.logged_out_set = true;
+            }
+            PrefEdit::ProfileName(name) => {
+                update.profile_name = Some(name);
+                update.profile_name_set = true;
+            }
+            PrefEdit::RunWebClient(val) => {
+                update.run_web_client = Some(val);
+                update.run_web_client_set = true;
+            }
+        }
+        update
+    }
 }
 
 fn is_false(b: &bool) -> bool {
@@ -318,12 +668,135 @@ pub struct PingResult {
     pub err: String,
 }
 
-/// One frame from the IPN notify bus. We only care about the auth URL field.
+/// Tailnet lock (TKA) status from `/localapi/v0/tka/status`.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct NetworkLockStatus {
+    /// Is tailnet lock enabled.
+    #[serde(default)]
+    pub enabled: bool,
+    /// This node's tailnet lock public key (`nlpub:...`).
+    #[serde(default)]
+    pub public_key: String,
+    /// This node's node key (`nodekey:...`).
+    #[serde(default)]
+    pub node_key: Option<String>,
+    /// Has this node's key been signed by a trusted key.
+    #[serde(default)]
+    pub node_key_signed: bool,
+    /// Keys trusted to sign nodes.
+    #[serde(default)]
+    pub trusted_keys: Option<Vec<TkaKey>>,
+    /// Peers filtered out because their node key is not signed.
+    #[serde(default)]
+    pub filtered_peers: Option<Vec<TkaPeer>>,
+}
+
+/// A key trusted by tailnet lock.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct TkaKey {
+    /// Public key (`nlpub:...`).
+    #[serde(default)]
+    pub key: String,
+    /// Free-form metadata attached when the key was added.
+    #[serde(default)]
+    pub metadata: Option<HashMap<String, String>>,
+    /// Voting weight of the key.
+    #[serde(default)]
+    pub votes: u64,
+}
+
+/// A peer as reported by tailnet lock.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct TkaPeer {
+    /// Name of the peer.
+    #[serde(default)]
+    pub name: String,
+    /// Stable node ID.
+    #[serde(rename = "StableID", default)]
+    pub stable_id: String,
+    /// Tailscale IP addresses.
+    #[serde(rename = "TailscaleIPs", default)]
+    pub tailscale_ips: Vec<String>,
+    /// The peer's node key (`nodekey:...`).
+    #[serde(default)]
+    pub node_key: String,
+}
+
+/// Body for POST `/localapi/v0/start`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct StartOptions<'a> {
+    auth_key: &'a str,
+    update_prefs: &'a serde_json::Value,
+}
+
+/// Body for POST `/localapi/v0/tka/sign`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct TkaSignRequest<'a> {
+    node_key: &'a str,
+}
+
+/// One frame from the IPN notify bus. We only care about the login fields.
 #[derive(Debug, Deserialize, Default)]
 #[serde(rename_all = "PascalCase")]
 struct Notify {
     #[serde(rename = "BrowseToURL", default)]
     browse_to_url: Option<String>,
+    #[serde(default)]
+    state: Option<IpnState>,
+    /// An empty object, sent once when a login completes.
+    #[serde(default)]
+    login_finished: Option<IgnoredAny>,
+    /// A login or backend error to show the user.
+    #[serde(default)]
+    err_message: Option<String>,
+}
+
+/// The daemon's backend state, sent as the integer values of Tailscale's
+/// `ipn.State`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(from = "u8")]
+pub enum IpnState {
+    #[default]
+    NoState,
+    InUseOtherUser,
+    /// Waiting for the user to visit the auth URL.
+    NeedsLogin,
+    /// Logged in, waiting for an admin to approve the device.
+    NeedsMachineAuth,
+    Stopped,
+    Starting,
+    Running,
+}
+
+impl From<u8> for IpnState {
+    fn from(state: u8) -> Self {
+        match state {
+            1 => IpnState::InUseOtherUser,
+            2 => IpnState::NeedsLogin,
+            3 => IpnState::NeedsMachineAuth,
+            4 => IpnState::Stopped,
+            5 => IpnState::Starting,
+            6 => IpnState::Running,
+            _ => IpnState::NoState,
+        }
+    }
+}
+
+/// Progress of an interactive login, from [`TailscaleClient::watch_login`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoginEvent {
+    /// The URL to visit to log in.
+    BrowseToUrl(String),
+    State(IpnState),
+    Finished,
+    /// The backend reported an error, e.g. the control server rejected an
+    /// auth key.
+    Error(String),
 }
 
 /// A client for the Tailscale LocalAPI over Unix socket.
@@ -355,6 +828,16 @@ impl TailscaleClient {
         self.request("PATCH", path, body).await
     }
 
+    /// Send a PUT request to the LocalAPI.
+    async fn put(&self, path: &str, body: Option<String>) -> TsResult<String> {
+        self.request("PUT", path, body).await
+    }
+
+    /// Send a DELETE request to the LocalAPI.
+    async fn delete(&self, path: &str) -> TsResult<String> {
+        self.request("DELETE", path, None).await
+    }
+
     /// Open a Unix-socket HTTP/1 connection to the LocalAPI and spawn the
     /// connection task. Returns the request `sender` ready to issue calls.
     async fn open_connection(&self) -> TsResult<SendRequest<Full<Bytes>>> {
@@ -421,8 +904,13 @@ impl TailscaleClient {
 
         let body_str = String::from_utf8_lossy(&body_bytes).to_string();
 
-        if status == 403 {
-            return Err(TailscaleError::ApiError(status, body_str));
+        // Surface the daemon's error text, e.g. "tag not permitted by ACL",
+        // rather than failing to parse it as the expected response.
+        if status >= 400 {
+            return Err(TailscaleError::ApiError(
+                status,
+                body_str.trim_end().to_string(),
+            ));
         }
 
         Ok(body_str)
@@ -472,6 +960,26 @@ impl TailscaleClient {
         Ok(())
     }
 
+    /// Current preferences as raw JSON, including fields [`Prefs`] doesn't
+    /// model.
+    pub async fn prefs_json(&self) -> TsResult<serde_json::Value> {
+        let body = self.get("/localapi/v0/prefs").await?;
+        serde_json::from_str(&body)
+            .map_err(|err| TailscaleError::ParseError(format!("prefs: {err}")))
+    }
+
+    /// (Re)start the backend with `prefs`, which replace the current prefs
+    /// wholesale, and an auth key for the next login.
+    pub async fn start(&self, auth_key: &str, prefs: &serde_json::Value) -> TsResult<()> {
+        let body = serde_json::to_string(&StartOptions {
+            auth_key,
+            update_prefs: prefs,
+        })
+        .map_err(|err| TailscaleError::ParseError(err.to_string()))?;
+        self.post("/localapi/v0/start", Some(body)).await?;
+        Ok(())
+    }
+
     /// Subscribe to the IPN notify bus and invoke `on_frame` for each parsed
     /// `Notify` frame. Returns when the stream is closed or errors; callers
     /// are responsible for reconnect/backoff. The bus opens with mask=14
@@ -480,10 +988,51 @@ impl TailscaleClient {
     pub async fn run_ipn_bus_listener<F>(&self, mut on_frame: F) -> TsResult<()>
     where
         F: FnMut() + Send,
+    {
+        self.stream_ipn_bus(14, |_| on_frame()).await
+    }
+
+    /// Follow an interactive login on the IPN notify bus, invoking
+    /// `on_event` with the auth URL, backend state changes and the login
+    /// finishing. The bus opens with mask=2 (`NotifyInitialState`), which
+    /// replays the current state and any pending auth URL, so it doesn't
+    /// matter whether the login was started before or after subscribing.
+    /// Returns when the stream is closed or errors; callers are responsible
+    /// for reconnect/backoff.
+    pub async fn watch_login<F>(&self, mut on_event: F) -> TsResult<()>
+    where
+        F: FnMut(LoginEvent) + Send,
+    {
+        self.stream_ipn_bus(2, |notify| {
+            if let Some(state) = notify.state {
+                on_event(LoginEvent::State(state));
+            }
+            if let Some(url) = notify.browse_to_url
+                && !url.is_empty()
+            {
+                on_event(LoginEvent::BrowseToUrl(url));
+            }
+            if notify.login_finished.is_some() {
+                on_event(LoginEvent::Finished);
+            }
+            if let Some(err) = notify.err_message
+                && !err.is_empty()
+            {
+                on_event(LoginEvent::Error(err));
+            }
+        })
+        .await
+    }
+
+    /// Stream newline-delimited `Notify` frames from the IPN bus with the
+    /// given `mask` until the stream closes or errors.
+    async fn stream_ipn_bus<F>(&self, mask: u32, mut on_notify: F) -> TsResult<()>
+    where
+        F: FnMut(Notify) + Send,
     {
         let mut sender = self.open_connection().await?;
 
-        let uri = format!("http://{LOCAL_API_HOST}/localapi/v0/watch-ipn-bus?mask=14");
+        let uri = format!("http://{LOCAL_API_HOST}/localapi/v0/watch-ipn-bus?mask={mask}");
         let req = Request::builder()
             .method("GET")
             .uri(&uri)
@@ -508,8 +1057,8 @@ impl TailscaleClient {
                     if line.is_empty() {
                         continue;
                     }
-                    if serde_json::from_slice::<Notify>(line).is_ok() {
-                        on_frame();
+                    if let Ok(notify) = serde_json::from_slice::<Notify>(line) {
+                        on_notify(notify);
                     }
                 }
             }
@@ -519,59 +1068,21 @@ impl TailscaleClient {
         ))
     }
 
-    /// Stream the IPN notify bus and return the first auth URL the daemon
-    /// publishes (a `BrowseToURL` notification). Used to drive the new-account
-    /// login flow inside a Flatpak sandbox where shelling out to the
-    /// `tailscale` CLI isn't available.
-    pub async fn wait_for_browse_url(&self, timeout: Duration) -> TsResult<String> {
-        tokio::time::timeout(timeout, self.read_browse_url())
-            .await
-            .map_err(|_| {
-                TailscaleError::RequestFailed("timed out waiting for auth URL".to_string())
-            })?
+    /// Get the tailnet lock status.
+    pub async fn tka_status(&self) -> TsResult<NetworkLockStatus> {
+        let body = self.get("/localapi/v0/tka/status").await?;
+        serde_json::from_str(&body)
+            .map_err(|err| TailscaleError::ParseError(format!("tka status: {err}")))
     }
 
-    async fn read_browse_url(&self) -> TsResult<String> {
-        let mut sender = self.open_connection().await?;
-
-        let uri = format!("http://{LOCAL_API_HOST}/localapi/v0/watch-ipn-bus?mask=0");
-        let req = Request::builder()
-            .method("GET")
-            .uri(&uri)
-            .header("Host", LOCAL_API_HOST)
-            .body(Full::new(Bytes::new()))
-            .map_err(|err| TailscaleError::RequestFailed(err.to_string()))?;
-
-        let response = sender
-            .send_request(req)
-            .await
-            .map_err(|err| TailscaleError::RequestFailed(err.to_string()))?;
+    /// Sign a peer's node key with this node's tailnet lock key. Only
+    /// succeeds when this node holds a trusted (signing) key.
+    pub async fn tka_sign(&self, node_key: &str) -> TsResult<()> {
+        let body = serde_json::to_string(&TkaSignRequest { node_key })
+            .map_err(|err| TailscaleError::ParseError(err.to_string()))?;
+        self.post("/localapi/v0/tka/sign", Some(body)).await?;
 
-        let mut body = response.into_body();
-        let mut buf: Vec<u8> = Vec::new();
-        while let Some(frame) = body.frame().await {
-            let frame = frame.map_err(|e| TailscaleError::RequestFailed(e.to_string()))?;
-            if let Ok(data) = frame.into_data() {
-                buf.extend_from_slice(&data);
-                while let Some(idx) = buf.iter().position(|b| *b == b'\n') {
-                    let line: Vec<u8> = buf.drain(..=idx).collect();
-                    let line = &line[..line.len() - 1];
-                    if line.is_empty() {
-                        continue;
-                    }
-                    if let Ok(notify) = serde_json::from_slice::<Notify>(line) {
-                        if let Some(url) = notify.browse_to_url {
-                            if !url.is_empty() {
-                                return Ok(url);
-                            }
-                        }
-                    }
-                }
-            }
-        }
-        Err(TailscaleError::RequestFailed(
-            "IPN bus closed before BrowseToURL was received".to_string(),
-        ))
+        Ok(())
     }
 
     /// Switch to a different profile/account.
@@ -582,6 +1093,27 @@ impl TailscaleClient {
         Ok(())
     }
 
+    /// Switch to a new, empty profile. It is saved once it logs in.
+    pub async fn new_profile(&self) -> TsResult<()> {
+        self.put("/localapi/v0/profiles/", None).await?;
+        Ok(())
+    }
+
+    /// Delete a saved profile. Deleting the current profile switches to an
+    /// empty one.
+    pub async fn delete_profile(&self, profile_id: &str) -> TsResult<()> {
+        self.delete(&format!("/localapi/v0/profiles/{profile_id}"))
+            .await?;
+        Ok(())
+    }
+
+    /// Log the current profile out; its node key is expired on the control
+    /// server.
+    pub async fn logout(&self) -> TsResult<()> {
+        self.post("/localapi/v0/logout", None).await?;
+        Ok(())
+    }
+
     /// Ping a peer.
     pub async fn ping(&self, ip: &str, ping_type: &str) -> TsResult<PingResult> {
         let body = self
@@ -718,6 +1250,41 @@ impl TailscaleClient {
         self.set_prefs(&prefs).await
     }
 
+    /// Set this node's hostname. An empty name falls back to the OS hostname.
+    pub async fn set_hostname(&self, hostname: &str) -> TsResult<Prefs> {
+        let prefs = PrefsUpdate {
+            hostname: Some(hostname.to_string()),
+            hostname_set: true,
+            ..Default::default()
+        };
+        self.set_prefs(&prefs).await
+    }
+
+    /// Apply a single typed preference change.
+    pub async fn set_pref(&self, edit: PrefEdit) -> TsResult<Prefs> {
+        self.set_prefs(&PrefsUpdate::from(edit)).await
+    }
+
+    /// Set the tags this node advertises. An empty list clears them.
+    pub async fn set_advertise_tags(&self, tags: Vec<String>) -> TsResult<Prefs> {
+        let prefs = PrefsUpdate {
+            advertise_tags: Some(tags),
+            advertise_tags_set: true,
+            ..Default::default()
+        };
+        self.set_prefs(&prefs).await
+    }
+
+    /// Enable/disable app connector mode.
+    pub async fn set_app_connector(&self, advertise: bool) -> TsResult<Prefs> {
+        let prefs = PrefsUpdate {
+            app_connector: Some(AppConnectorPrefs { advertise }),
+            app_connector_set: true,
+            ..Default::default()
+        };
+        self.set_prefs(&prefs).await
+    }
+
     /// Set accept-routes enabled/disabled.
     pub async fn set_accept_routes(&self, accept: bool) -> TsResult<Prefs> {
         let prefs = PrefsUpdate {
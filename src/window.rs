@@ -1,56 +1,158 @@
 use crate::{
-    config::{APP_ID, AppPreferences, CONFIG_VERS, load_preferences, update_config},
+    config::{
+        APP_ID, AppPreferences, CONFIG_VERS, ProfilePrefs, changed_keys, export_preferences,
+        format_reminder_days, import_preferences, load_preferences, merge_keys,
+        parse_reminder_days,
+    },
+    dbus::{DbusState, publish_state, serve},
     fl,
     logic::{
-        PingResult, TailscaleState, clear_status, copy_to_clipboard, default_download_dir,
-        fetch_state, format_bytes, login_new_account, ping_device, receive_files, send_files,
-        set_advertise_exit_node, set_advertised_routes, set_connected, set_exit_node,
-        set_exit_node_allow_lan, set_magic_dns, set_routes, set_ssh, switch_account,
+        AuthKeyLogin, DeviceFilter, DeviceGrouping, DeviceSort, LoginStage, PauseDuration,
+        PingResult, Presence, PresenceChange, PresenceWatch, TailscaleState, clear_status,
+        copy_to_clipboard, days_until, default_download_dir, delete_account, device_view,
+        due_key_expiry_reminder, fetch_state, format_bytes, login_new_account, login_with_auth_key,
+        logout_account, open_ssh_session, open_url, parse_schedule_time, parse_tag, pause_until,
+        ping_device, preview_fqdn, reauthenticate, receive_files, schedule_crossed, send_files,
+        set_advertise_exit_node, set_advertise_tags, set_advertised_routes, set_app_connector,
+        set_connected, set_exit_node, set_exit_node_allow_lan, set_hostname, set_magic_dns,
+        set_pref, set_routes, set_ssh, sftp_url, sign_node, switch_account, valid_control_url,
+        valid_dns_label, valid_operator_user,
+    },
+    network::{
+        ExitNodeRule, NetworkInfo, NetworkMatch, NetworkRule, RuleAction, matching_rule,
+        watch_primary_network,
+    },
+    notifications::{
+        DEFAULT_ICON, DesktopNotifier, NotificationKind, NotificationStyle, Notifications,
+    },
+    routes::{
+        Cidr, PeerRoute, PeerRouteStatus, local_networks, peer_routes, route_overlaps,
+        validate_new_route,
     },
-    notifications::*,
-    tailscale_api::TailscaleClient,
+    shortcuts::{set_global_shortcuts, shortcut_labels},
+    state_diff::{Correction, EnforcedPrefs, StateChange, StateDiff, drift},
+    tailscale_api::{AuthKeyError, LoginEvent, NetfilterMode, PrefEdit, TailscaleClient},
 };
+use chrono::{DateTime, Local, NaiveTime, TimeZone, Utc};
 use cosmic::{
     Action, Element, Task,
     app::Core,
-    cosmic_config::Config,
+    cosmic_config::{self, Config, CosmicConfigEntry},
     dialog::file_chooser::{self, FileFilter},
     iced::{
         self, Alignment, Length, Limits, Subscription,
         core::window,
+        futures::SinkExt,
         platform_specific::shell::commands::popup::{destroy_popup, get_popup},
-        widget::{column, row},
+        widget::{column, qr_code, row},
         window::Id,
     },
     task,
     widget::{
-        button, container, dropdown, icon, list_column, scrollable, settings, text, text_input,
-        toggler,
+        button, container, dropdown, icon, list_column, mouse_area, scrollable, settings, text,
+        text_input, toggler,
     },
 };
-use std::{fmt::Debug, time::Duration};
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use url::Url;
 
 const POPUP_MAX_WIDTH: f32 = 1440.0;
 const POPUP_MIN_WIDTH: f32 = 480.0;
 const POPUP_MAX_HEIGHT: f32 = 720.0;
 const POPUP_MIN_HEIGHT: f32 = 640.0;
+const QUICK_SWITCH_MAX_WIDTH: f32 = 360.0;
+const QUICK_SWITCH_MIN_WIDTH: f32 = 240.0;
 const STATUS_CLEAR_TIME: u64 = 5;
+/// Polling slows down by this factor while the IPN bus delivers updates.
+const HEALTHY_BUS_POLL_BACKOFF: u64 = 6;
+/// Longest accepted polling interval.
+const MAX_POLL_INTERVAL_SECS: u64 = 3600;
+/// Longest accepted hold time for presence changes.
+const MAX_PRESENCE_DEBOUNCE_SECS: u64 = 3600;
+/// How long typing in a settings text input must pause before it is saved.
+const INPUT_SAVE_DELAY: Duration = Duration::from_millis(750);
+/// How long a config change to a key the applet just wrote is taken to be
+/// the echo of that write.
+const OWN_WRITE_ECHO: Duration = Duration::from_secs(2);
+/// How often pauses and schedules are checked.
+const SCHEDULE_TICK: Duration = Duration::from_secs(30);
+/// When "pause until tomorrow" ends if no connect schedule is set.
+const DEFAULT_RESUME_TIME: NaiveTime = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
+/// Suggested name for exported settings.
+const SETTINGS_FILE_NAME: &str = "gui-scale-applet-settings.json";
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Tab {
     Status,
     TailDrop,
     Devices,
+    Routes,
+    TailnetLock,
     Settings,
 }
 
+/// What the open popup shows: the full tabbed window, or the account
+/// quick-switch menu opened by right-clicking the panel icon.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum PopupKind {
+    #[default]
+    Full,
+    QuickSwitch,
+}
+
+/// An interactive login in progress, shown on the Status tab until the
+/// IPN bus reports it done.
+#[derive(Debug, Default)]
+struct LoginFlow {
+    stage: LoginStage,
+    url: Option<String>,
+    qr: Option<qr_code::Data>,
+    /// Why the auth URL couldn't be opened in a browser.
+    open_error: Option<String>,
+    /// Why the daemon refused to start the login.
+    error: Option<String>,
+    /// Logging in with an auth key rather than in a browser.
+    auth_key: bool,
+}
+
+/// The "Log in with auth key" form in Settings.
+#[derive(Debug, Default)]
+struct AuthKeyForm {
+    auth_key: String,
+    tags: String,
+    hostname: String,
+    control_url: String,
+    error: String,
+}
+
+/// A network rule being composed in Settings; indices into the fixed rule
+/// dropdowns. The exit node is kept by name, since the list of peers it
+/// was picked from is rebuilt with every state refresh.
+#[derive(Debug, Default)]
+struct RuleDraft {
+    match_idx: usize,
+    value: String,
+    action_idx: usize,
+    exit_node: ExitNodeRule,
+}
+
 /// Holds the applet's state
 pub struct Window {
     core: Core,
     config: Config,
+    /// Config keys the applet wrote, and when.
+    own_writes: HashMap<String, Instant>,
     client: TailscaleClient,
     popup: Option<Id>,
+    popup_kind: PopupKind,
+    shortcuts_status: String,
+    /// Result of the last settings import or export.
+    settings_file_status: String,
     state: TailscaleState,
     active_tab: Tab,
     selected_device_idx: Option<usize>,
@@ -66,11 +168,63 @@ pub struct Window {
     ping_result: Option<PingResult>,
     ping_in_progress: bool,
     subnet_input: String,
+    subnet_error: String,
+    hostname_draft: Option<String>,
+    hostname_status: String,
+    local_networks: Vec<(String, Cidr)>,
+    tag_input: String,
+    node_prefs_status: String,
+    show_advanced: bool,
+    show_notification_styles: bool,
+    netfilter_mode_names: Vec<String>,
+    operator_user_draft: Option<String>,
+    control_url_draft: Option<String>,
+    profile_name_draft: Option<String>,
+    advanced_status: String,
+    login_control_url: String,
+    login: Option<LoginFlow>,
+    auth_key_form: Option<AuthKeyForm>,
+    confirm_delete_account: Option<String>,
+    account_status: String,
     preferences: AppPreferences,
-    previous_connected_state: bool,
-    previous_device_count: usize,
+    notifications: Notifications,
     notifications_initialized: bool,
+    flash: Option<(u64, String)>,
+    flash_seq: u64,
     initial_load_done: bool,
+    key_expiry_input: String,
+    terminal_input: String,
+    ssh_user_input: String,
+    presence_debounce_input: String,
+    /// Bumped on every keystroke in a settings text input.
+    input_save_seq: u64,
+    poll_interval_input: String,
+    ipn_bus_healthy: bool,
+    /// Poll ticks received while the IPN bus was healthy.
+    healthy_poll_ticks: u64,
+    /// Enforced preferences are due to be checked once connected.
+    enforce_pending: bool,
+    dbus: Option<zbus::Connection>,
+    current_network: Option<NetworkInfo>,
+    rule_draft: RuleDraft,
+    last_schedule_check: DateTime<Local>,
+    schedule_connect_input: String,
+    schedule_disconnect_input: String,
+    schedule_error: String,
+    rule_match_names: Vec<String>,
+    rule_action_names: Vec<String>,
+    rule_exit_names: Vec<String>,
+    tka_status: String,
+    device_query: String,
+    device_filters: Vec<DeviceFilter>,
+    device_grouping: DeviceGrouping,
+    device_grouping_names: Vec<String>,
+    device_sort: DeviceSort,
+    device_sort_names: Vec<String>,
+    device_latencies: HashMap<String, f64>,
+    presence_watch: PresenceWatch,
+    /// When the scheduled `PresenceTick` fires, while one is outstanding.
+    presence_deadline: Option<Instant>,
 }
 
 /// Messages to be sent to the Libcosmic Update function
@@ -78,12 +232,44 @@ pub struct Window {
 pub enum Message {
     // Popup
     TogglePopup,
+    ToggleQuickSwitch,
+    QuickSwitchAccount(usize),
     PopupClosed(Id),
     TabSelected(Tab),
 
     // Polling
     IpnEvent,
+    IpnBusHealth(bool),
+    PollTick,
+    PollIntervalInput(String),
     StateLoaded(Result<TailscaleState, String>),
+    ClearFlash(u64),
+
+    // D-Bus
+    DbusReady(zbus::Connection),
+
+    // Shields up
+    NetworkChanged(Option<NetworkInfo>),
+    LocalNetworksLoaded(Vec<(String, Cidr)>),
+    ToggleTrustedNetwork(String),
+    SetAutoShieldsUp(bool),
+    SetGlobalShortcuts(bool),
+    ConfigChanged(Vec<String>, AppPreferences),
+
+    // Pause and schedules
+    Pause(PauseDuration),
+    Resume,
+    ScheduleTick,
+    ScheduleConnectInput(String),
+    ScheduleDisconnectInput(String),
+
+    // Network rules
+    RuleMatchSelected(usize),
+    RuleValueInput(String),
+    RuleActionSelected(usize),
+    RuleExitNodeSelected(usize),
+    AddNetworkRule,
+    RemoveNetworkRule(usize),
 
     // Connection
     EnableSSH(bool),
@@ -94,6 +280,24 @@ pub enum Message {
     // Accounts
     SwitchAccount(usize),
     LoginNewAccount,
+    LoginControlUrlInput(String),
+    LoginStarted(Result<(), String>),
+    LoginProgress(LoginEvent),
+    CancelLogin,
+    LoginDismissed,
+    ToggleAuthKeyForm,
+    AuthKeyInput(String),
+    AuthKeyTagsInput(String),
+    AuthKeyHostnameInput(String),
+    AuthKeyControlUrlInput(String),
+    SubmitAuthKey,
+    AuthKeyLoginDone(Result<(), AuthKeyError>),
+    LogoutAccount,
+    DeleteAccount(String),
+    ConfirmDeleteAccount,
+    CancelDeleteAccount,
+    AccountActionDone(Result<(), String>),
+    Reauthenticate,
 
     // Tails Drop
     DeviceSelected(usize),
@@ -116,22 +320,83 @@ pub enum Message {
     PingDevice(String),
     PingCompleted(Result<PingResult, String>),
     CopyToClipboard(String),
+    OpenSsh(String, bool),
+    OpenUrl(String),
+
+    // Favorites
+    ToggleFavorite(String),
+    QuickSend(String),
+    SetNotifyFavoritePresence(bool),
+
+    // Presence watch list
+    ToggleWatch(String),
+    PresenceTick,
+
+    // Device list
+    DeviceSearch(String),
+    ToggleDeviceFilter(DeviceFilter),
+    DeviceGroupingSelected(usize),
+    DeviceSortSelected(usize),
+
+    // Tailnet lock
+    SignNode(String),
+    NodeSigned(Result<(), String>),
+
+    // Hostname
+    HostnameInput(String),
+    SaveHostname,
+    HostnameSaved(Result<(), String>),
 
     // Subnets
     SubnetInput(String),
     AddSubnet,
     RemoveSubnet(usize),
 
+    // Node tags and app connector
+    TagInput(String),
+    AddTag,
+    RemoveTag(usize),
+    SetAppConnector(bool),
+    NodePrefsSet(Result<(), String>),
+
+    // Advanced daemon prefs
+    ToggleAdvanced,
+    SetPref(PrefEdit),
+    PrefSet(Result<(), String>),
+    NetfilterModeSelected(usize),
+    OperatorUserInput(String),
+    ApplyOperatorUser,
+    ControlUrlInput(String),
+    ApplyControlUrl,
+    ProfileNameInput(String),
+    ApplyProfileName,
+
     // Settings
     SetAutoConnect(bool),
+    SetEnforcePrefs(bool),
     SetNotificationsEnabled(bool),
     SetNotifyConnection(bool),
     SetNotifyFiles(bool),
     SetNotifyDevice(bool),
+    SetNotifyKeyExpiry(bool),
+    ToggleNotificationStyles,
+    NotificationIconInput(NotificationKind, String),
+    NotificationSoundInput(NotificationKind, String),
+    KeyExpiryRemindersInput(String),
     SetIconStyle(bool),
+    TerminalInput(String),
+    SshUserInput(String),
+    PresenceDebounceInput(String),
+    /// Save the settings text inputs, unless typing continued since.
+    SaveInputs(u64),
     ChooseDownloadDir,
     DownloadDirSelected(Vec<Url>),
     DownloadDirCancelled,
+    ExportSettings,
+    ExportPathSelected(Url),
+    ImportSettings,
+    ImportPathSelected(Url),
+    SettingsFileCancelled,
 
     ActionCompleted(Result<(), String>),
 }
@@ -152,14 +417,26 @@ impl cosmic::Application for Window {
 
     fn init(core: Core, _flags: Self::Flags) -> (Window, Task<Action<Self::Message>>) {
         let client = TailscaleClient::new();
-        let preferences = load_preferences();
+        let config = Config::new(APP_ID, CONFIG_VERS).unwrap();
+        let preferences = load_preferences(&config);
+        let key_expiry_input = format_reminder_days(&preferences.key_expiry_reminder_days);
+        let terminal_input = preferences.terminal.clone();
+        let ssh_user_input = preferences.ssh_user.clone();
+        let presence_debounce_input = preferences.presence_debounce_secs.to_string();
+        let schedule_connect_input = preferences.schedule_connect.clone();
+        let schedule_disconnect_input = preferences.schedule_disconnect.clone();
+        let poll_interval_input = preferences.poll_interval_secs.to_string();
 
         // Set the start up state of the application using the above variables
         let window = Window {
             core,
-            config: Config::new(APP_ID, CONFIG_VERS).unwrap(),
+            config,
+            own_writes: HashMap::new(),
             client: client.clone(),
             popup: None,
+            popup_kind: PopupKind::Full,
+            shortcuts_status: String::new(),
+            settings_file_status: String::new(),
             state: TailscaleState::default(),
             active_tab: Tab::Status,
             selected_device_idx: Some(0),
@@ -175,11 +452,81 @@ impl cosmic::Application for Window {
             ping_result: None,
             ping_in_progress: false,
             subnet_input: String::new(),
+            subnet_error: String::new(),
+            hostname_draft: None,
+            hostname_status: String::new(),
+            local_networks: Vec::new(),
+            tag_input: String::new(),
+            node_prefs_status: String::new(),
+            show_advanced: false,
+            show_notification_styles: false,
+            netfilter_mode_names: vec![
+                fl!("advanced-netfilter-off"),
+                fl!("advanced-netfilter-nodivert"),
+                fl!("advanced-netfilter-on"),
+            ],
+            operator_user_draft: None,
+            control_url_draft: None,
+            profile_name_draft: None,
+            advanced_status: String::new(),
+            login_control_url: String::new(),
+            login: None,
+            auth_key_form: None,
+            confirm_delete_account: None,
+            account_status: String::new(),
+            notifications: Notifications::new(
+                Arc::new(DesktopNotifier::default()),
+                preferences.notification_styles.clone(),
+            ),
             preferences,
-            previous_connected_state: false,
-            previous_device_count: 0,
             notifications_initialized: false,
+            flash: None,
+            flash_seq: 0,
             initial_load_done: false,
+            key_expiry_input,
+            terminal_input,
+            ssh_user_input,
+            presence_debounce_input,
+            input_save_seq: 0,
+            poll_interval_input,
+            ipn_bus_healthy: false,
+            healthy_poll_ticks: 0,
+            enforce_pending: false,
+            dbus: None,
+            current_network: None,
+            rule_draft: RuleDraft::default(),
+            last_schedule_check: Local::now(),
+            schedule_connect_input,
+            schedule_disconnect_input,
+            schedule_error: String::new(),
+            rule_match_names: vec![
+                fl!("rules-match-ssid"),
+                fl!("rules-match-gateway"),
+                fl!("rules-match-interface"),
+                fl!("rules-match-untrusted"),
+            ],
+            rule_action_names: vec![fl!("rules-connect"), fl!("rules-disconnect")],
+            rule_exit_names: vec![fl!("rules-exit-keep"), fl!("rules-exit-none")],
+            tka_status: String::new(),
+            device_query: String::new(),
+            device_filters: Vec::new(),
+            device_grouping: DeviceGrouping::default(),
+            device_grouping_names: vec![
+                fl!("devices-group-none"),
+                fl!("devices-group-os"),
+                fl!("devices-group-tag"),
+                fl!("devices-group-owner"),
+            ],
+            device_sort: DeviceSort::default(),
+            device_sort_names: vec![
+                fl!("devices-sort-name"),
+                fl!("devices-sort-last-seen"),
+                fl!("devices-sort-traffic"),
+                fl!("devices-sort-latency"),
+            ],
+            device_latencies: HashMap::new(),
+            presence_watch: PresenceWatch::default(),
+            presence_deadline: None,
         };
 
         // Kick off the initial async state load
@@ -200,56 +547,172 @@ impl cosmic::Application for Window {
     }
 
     fn subscription(&self) -> Subscription<Self::Message> {
-        Subscription::run_with("ipn-bus", |_| {
+        let ipn_bus = Subscription::run_with("ipn-bus", |_| {
             let client = TailscaleClient::new();
             iced::stream::channel(
                 64,
                 move |output: iced::futures::channel::mpsc::Sender<Message>| async move {
                     loop {
                         let mut sender = output.clone();
+                        let mut healthy = false;
                         let result = client
                             .run_ipn_bus_listener(move || {
+                                if !healthy {
+                                    healthy = true;
+                                    let _ = sender.try_send(Message::IpnBusHealth(true));
+                                }
                                 let _ = sender.try_send(Message::IpnEvent);
                             })
                             .await;
                         if let Err(e) = result {
                             eprintln!("IPN bus listener disconnected: {e}");
                         }
+                        let _ = output.clone().try_send(Message::IpnBusHealth(false));
                         tokio::time::sleep(Duration::from_secs(2)).await;
                     }
                 },
             )
-        })
+        });
+
+        // Fallback polling: at the configured interval while the IPN bus is
+        // down (older daemons, permission issues), and only occasionally while
+        // it delivers updates. The ticks don't depend on the bus health, so a
+        // flapping bus doesn't keep restarting them.
+        let poll_secs = self
+            .preferences
+            .poll_interval_secs
+            .clamp(1, MAX_POLL_INTERVAL_SECS);
+        let poll = Subscription::run_with(("poll", poll_secs), |&(_, secs)| {
+            iced::stream::channel(
+                1,
+                move |mut output: iced::futures::channel::mpsc::Sender<Message>| async move {
+                    let mut interval = tokio::time::interval(Duration::from_secs(secs));
+                    // The first tick fires immediately; state is loaded on start.
+                    interval.tick().await;
+                    loop {
+                        interval.tick().await;
+                        let _ = output.send(Message::PollTick).await;
+                    }
+                },
+            )
+        });
+
+        // Serve the session D-Bus interface. The connection is handed to the
+        // applet so it can publish state; this stream then idles to keep the
+        // subscription (and the bus name) alive.
+        let dbus_service = Subscription::run_with("dbus-service", |_| {
+            iced::stream::channel(
+                1,
+                move |mut output: iced::futures::channel::mpsc::Sender<Message>| async move {
+                    match serve(TailscaleClient::new()).await {
+                        Ok(conn) => {
+                            let _ = output.send(Message::DbusReady(conn)).await;
+                        }
+                        Err(e) => eprintln!("Failed to start D-Bus service: {e}"),
+                    }
+                    std::future::pending::<()>().await;
+                },
+            )
+        });
+
+        // Follow a login in progress; the panel closes shortly after it's
+        // done.
+        let login = if self
+            .login
+            .as_ref()
+            .is_some_and(|login| login.stage != LoginStage::Done)
+        {
+            Subscription::run_with("login-watch", |_| {
+                let client = TailscaleClient::new();
+                iced::stream::channel(
+                    16,
+                    move |output: iced::futures::channel::mpsc::Sender<Message>| async move {
+                        loop {
+                            let mut sender = output.clone();
+                            let result = client
+                                .watch_login(move |event| {
+                                    let _ = sender.try_send(Message::LoginProgress(event));
+                                })
+                                .await;
+                            if let Err(e) = result {
+                                eprintln!("Login watch disconnected: {e}");
+                            }
+                            tokio::time::sleep(Duration::from_secs(2)).await;
+                        }
+                    },
+                )
+            })
+        } else {
+            Subscription::none()
+        };
+
+        // Follow the primary network for the trusted-network rules.
+        let network = Subscription::run_with("network", |_| {
+            iced::stream::channel(
+                8,
+                move |output: iced::futures::channel::mpsc::Sender<Message>| async move {
+                    loop {
+                        let mut sender = output.clone();
+                        let result = watch_primary_network(move |network| {
+                            let _ = sender.try_send(Message::NetworkChanged(network));
+                        })
+                        .await;
+                        if let Err(e) = result {
+                            eprintln!("NetworkManager watch failed: {e}");
+                        }
+                        tokio::time::sleep(Duration::from_secs(30)).await;
+                    }
+                },
+            )
+        });
+
+        // Drives pause expiry and the daily schedules.
+        let schedule = Subscription::run_with("schedule-tick", |_| {
+            iced::stream::channel(
+                1,
+                move |mut output: iced::futures::channel::mpsc::Sender<Message>| async move {
+                    let mut interval = tokio::time::interval(SCHEDULE_TICK);
+                    loop {
+                        interval.tick().await;
+                        let _ = output.send(Message::ScheduleTick).await;
+                    }
+                },
+            )
+        });
+
+        // Preferences changed outside the applet.
+        let config = self
+            .core
+            .watch_config::<AppPreferences>(APP_ID)
+            .map(|update| {
+                for err in update.errors.iter().filter(|err| err.is_err()) {
+                    eprintln!("Failed to reload preference: {err}");
+                }
+                Message::ConfigChanged(update.keys, update.config)
+            });
+
+        Subscription::batch([
+            ipn_bus,
+            poll,
+            dbus_service,
+            network,
+            schedule,
+            login,
+            config,
+        ])
     }
 
     // Libcosmic's update function
     fn update(&mut self, message: Self::Message) -> Task<Action<Self::Message>> {
         let mut tasks: Vec<Task<Action<Message>>> = Vec::new();
         match message {
-            Message::TogglePopup => {
-                return if let Some(p) = self.popup.take() {
-                    self.receive_file_status = String::new();
-                    destroy_popup(p)
-                } else {
-                    let new_id = Id::unique();
-                    self.popup.replace(new_id);
-
-                    let mut popup_settings = self.core.applet.get_popup_settings(
-                        self.core.main_window_id().unwrap(),
-                        new_id,
-                        None,
-                        None,
-                        None,
-                    );
-
-                    popup_settings.positioner.size_limits = Limits::NONE
-                        .max_width(POPUP_MAX_WIDTH)
-                        .min_width(POPUP_MIN_WIDTH)
-                        .min_height(POPUP_MIN_HEIGHT)
-                        .max_height(POPUP_MAX_HEIGHT);
-
-                    get_popup(popup_settings)
-                };
+            Message::TogglePopup => return self.toggle_popup(PopupKind::Full),
+            Message::ToggleQuickSwitch => return self.toggle_popup(PopupKind::QuickSwitch),
+            Message::QuickSwitchAccount(idx) => {
+                if let Some(p) = self.popup.take() {
+                    tasks.push(destroy_popup(p));
+                }
+                tasks.push(task::future(async move { Message::SwitchAccount(idx) }));
             }
             Message::TabSelected(tab) => {
                 self.active_tab = tab;
@@ -259,7 +722,20 @@ impl cosmic::Application for Window {
                     self.popup = None;
                 }
             }
-            Message::IpnEvent => {
+            Message::IpnBusHealth(healthy) => {
+                // The bus (re)connects on start and after a daemon restart.
+                if healthy && !self.ipn_bus_healthy {
+                    self.enforce_pending = true;
+                }
+                self.ipn_bus_healthy = healthy;
+            }
+            Message::PollTick if self.ipn_bus_healthy => {
+                self.healthy_poll_ticks += 1;
+                if self.healthy_poll_ticks % HEALTHY_BUS_POLL_BACKOFF == 0 {
+                    tasks.push(task::future(async move { Message::IpnEvent }));
+                }
+            }
+            Message::IpnEvent | Message::PollTick => {
                 let client = self.client.clone();
                 tasks.push(task::future(async move {
                     match fetch_state(&client).await {
@@ -271,35 +747,63 @@ impl cosmic::Application for Window {
             Message::StateLoaded(result) => {
                 match result {
                     Ok(new_state) => {
-                        // Notifications for state changes
-                        if self.notifications_initialized && self.preferences.notifications_enabled
-                        {
-                            if self.preferences.notify_on_connection_change
-                                && new_state.connected != self.previous_connected_state
-                            {
-                                notify_connection_change(new_state.connected);
+                        // Notifications and UI flashes for state changes.
+                        // The first snapshot is diffed against the empty
+                        // default state, so skip it.
+                        let diff = StateDiff::between(&self.state, &new_state);
+                        if self.notifications_initialized && !diff.is_empty() {
+                            if self.preferences.notifications_enabled {
+                                self.notify_state_changes(&diff);
                             }
-
-                            if self.preferences.notify_on_new_device
-                                && new_state.devices.len() > self.previous_device_count
-                            {
-                                for dev in &new_state.devices {
-                                    if !self.state.devices.iter().any(|device| device.id == dev.id)
-                                    {
-                                        notify_new_device(&dev.name);
-                                    }
-                                }
+                            if let Some(flash) = diff.changes.iter().find_map(flash_text) {
+                                tasks.push(self.show_flash(flash));
                             }
+                        }
+                        if diff.changes.contains(&StateChange::Connected) || diff.account_switched()
+                        {
+                            self.enforce_pending = true;
+                        }
 
-                            if self.preferences.notify_on_incoming_files
-                                && !new_state.waiting_files.is_empty()
-                            {
-                                notify_incoming_files();
-                            }
+                        // Remind once per configured threshold before this
+                        // node's key expires. A renewed key has a new expiry,
+                        // which resets the reminders.
+                        let now = Utc::now();
+                        if let Some(expiry) = new_state.self_key_expiry
+                            && self.preferences.notifications_enabled
+                            && self.preferences.notify_on_key_expiry
+                            && let Some(days) = due_key_expiry_reminder(
+                                expiry,
+                                now,
+                                &self.preferences.key_expiry_reminder_days,
+                                self.preferences
+                                    .key_expiry_reminded
+                                    .filter(|(reminded_expiry, _)| {
+                                        *reminded_expiry == expiry.timestamp()
+                                    })
+                                    .map(|(_, days)| days),
+                            )
+                        {
+                            let reminded = Some((expiry.timestamp(), days));
+                            self.save_pref(|prefs, config| {
+                                prefs.set_key_expiry_reminded(config, reminded)
+                            });
+                            let days_left = days_until(expiry, now);
+                            let notifications = self.notifications.clone();
+                            tasks.push(task::future(async move {
+                                let reauth = tokio::task::spawn_blocking(move || {
+                                    notifications.key_expiry(days_left)
+                                })
+                                .await
+                                .unwrap_or(false);
+
+                                if reauth {
+                                    Message::Reauthenticate
+                                } else {
+                                    Message::ActionCompleted(Ok(()))
+                                }
+                            }));
                         }
 
-                        self.previous_connected_state = new_state.connected;
-                        self.previous_device_count = new_state.devices.len();
                         self.notifications_initialized = true;
 
                         // Update derived UI state. Format account labels as
@@ -324,9 +828,27 @@ impl cosmic::Application for Window {
                         }
                         self.exit_node_names = en_names;
 
-                        // Auto-connect on first load if configured
-                        if !self.initial_load_done
+                        let mut rule_exit_names =
+                            vec![fl!("rules-exit-keep"), fl!("rules-exit-none")];
+                        rule_exit_names.extend(
+                            new_state
+                                .exit_node_options
+                                .iter()
+                                .map(|dev| dev.name.clone()),
+                        );
+                        self.rule_exit_names = rule_exit_names;
+
+                        if diff.account_switched() {
+                            tasks.extend(self.switch_profile_prefs(&new_state));
+                        }
+
+                        // Auto-connect on first load if configured, unless a
+                        // network rule decides instead.
+                        let first_load = !self.initial_load_done;
+                        if first_load
                             && self.preferences.auto_connect
+                            && self.matching_network_rule().is_none()
+                            && !self.is_paused()
                             && !new_state.connected
                         {
                             let client = self.client.clone();
@@ -338,6 +860,54 @@ impl cosmic::Application for Window {
 
                         self.initial_load_done = true;
                         self.state = new_state;
+                        if first_load {
+                            // The network may have been reported before
+                            // there was any state to apply rules to.
+                            tasks.extend(self.apply_network_rules());
+                            tasks.push(load_local_networks());
+                        }
+                        if self.enforce_pending && self.state.connected {
+                            self.enforce_pending = false;
+                            if self.preferences.enforce_prefs {
+                                tasks.extend(self.enforce_preferences());
+                            }
+                        }
+
+                        tasks.extend(self.publish_dbus_state());
+
+                        // Watched peers seen for the first time set their
+                        // baseline; after that the diff's presence changes
+                        // feed the debouncer, which notifies them once they
+                        // have held long enough.
+                        let watched = self.watched_ids();
+                        self.presence_watch.retain(&watched);
+                        let now = Instant::now();
+                        for dev in self.state.devices.iter() {
+                            if watched.contains(&dev.id) && !self.presence_watch.knows(&dev.id) {
+                                self.presence_watch
+                                    .observe(&dev.id, Presence::from(dev), now);
+                            }
+                        }
+                        for change in &diff.changes {
+                            match change {
+                                StateChange::DeviceOnline { id, .. }
+                                | StateChange::DeviceOffline { id, .. }
+                                | StateChange::DeviceConnectionChanged { id, .. } => {
+                                    if watched.contains(id)
+                                        && let Some(dev) =
+                                            self.state.devices.iter().find(|dev| dev.id == *id)
+                                    {
+                                        self.presence_watch.observe(id, Presence::from(dev), now);
+                                    }
+                                }
+                                // A peer that rejoins starts from a new baseline.
+                                StateChange::DeviceRemoved { id, .. } => {
+                                    self.presence_watch.forget(id);
+                                }
+                                _ => {}
+                            }
+                        }
+                        tasks.extend(self.settle_presence());
                     }
                     Err(e) => {
                         if e.contains("not found") || e.contains("Socket") {
@@ -350,7 +920,18 @@ impl cosmic::Application for Window {
                     }
                 }
             }
+            Message::ClearFlash(seq) => {
+                // A newer flash replaced this one; let its own timer clear it.
+                if self.flash.as_ref().is_some_and(|(shown, _)| *shown == seq) {
+                    self.flash = None;
+                }
+            }
+            Message::DbusReady(conn) => {
+                self.dbus = Some(conn);
+                tasks.extend(self.publish_dbus_state());
+            }
             Message::EnableSSH(enabled) => {
+                self.save_pref(|prefs, config| prefs.set_ssh_enabled(config, enabled));
                 let client = self.client.clone();
                 tasks.push(task::future(async move {
                     match set_ssh(&client, enabled).await {
@@ -360,6 +941,7 @@ impl cosmic::Application for Window {
                 }));
             }
             Message::AcceptRoutes(accepted) => {
+                self.save_pref(|prefs, config| prefs.set_routes_accepted(config, accepted));
                 let client = self.client.clone();
                 tasks.push(task::future(async move {
                     match set_routes(&client, accepted).await {
@@ -369,21 +951,18 @@ impl cosmic::Application for Window {
                 }));
             }
             Message::ConnectDisconnect(connection) => {
+                // Connecting or disconnecting by hand ends a pause.
+                self.set_paused_until(None);
                 let client = self.client.clone();
-                let notify = self.preferences.notifications_enabled
-                    && self.preferences.notify_on_connection_change;
                 tasks.push(task::future(async move {
-                    let result = set_connected(&client, connection).await;
-                    if notify && result.is_ok() {
-                        notify_connection_change(connection);
-                    }
-                    match result {
+                    match set_connected(&client, connection).await {
                         Ok(()) => Message::ActionCompleted(Ok(())),
                         Err(e) => Message::ActionCompleted(Err(format!("set_connected: {e}"))),
                     }
                 }));
             }
             Message::ToggleMagicDns(enabled) => {
+                self.save_pref(|prefs, config| prefs.set_magic_dns(config, enabled));
                 let client = self.client.clone();
                 tasks.push(task::future(async move {
                     match set_magic_dns(&client, enabled).await {
@@ -396,24 +975,214 @@ impl cosmic::Application for Window {
                 if let Some(acct) = self.state.accounts.get(new_acct) {
                     let client = self.client.clone();
                     let profile_id = acct.id.clone();
-                    let acct_name = acct.name.clone();
-                    let notify = self.preferences.notifications_enabled;
                     tasks.push(task::future(async move {
                         let _ = switch_account(&client, &profile_id).await;
-                        if notify {
-                            notify_account_switched(&acct_name);
-                        }
                         Message::ActionCompleted(Ok(()))
                     }));
                 }
             }
             Message::LoginNewAccount => {
+                let control_url = self
+                    .login_control_url
+                    .trim()
+                    .trim_end_matches('/')
+                    .to_string();
+                if !valid_control_url(&control_url) {
+                    self.account_status = fl!("advanced-control-url-invalid");
+                    return Task::none();
+                }
+                self.account_status.clear();
+                self.login = Some(LoginFlow::default());
                 let client = self.client.clone();
                 tasks.push(task::future(async move {
-                    match login_new_account(&client).await {
-                        Ok(()) => Message::ActionCompleted(Ok(())),
-                        Err(e) => Message::ActionCompleted(Err(e.to_string())),
+                    Message::LoginStarted(
+                        login_new_account(&client, &control_url)
+                            .await
+                            .map_err(|e| e.to_string()),
+                    )
+                }));
+            }
+            Message::LoginControlUrlInput(val) => {
+                self.login_control_url = val;
+            }
+            Message::LoginStarted(result) => {
+                if let (Err(e), Some(login)) = (result, &mut self.login) {
+                    login.error = Some(format!("{}: {e}", fl!("accounts-login-failed")));
+                }
+            }
+            Message::LoginProgress(event) => {
+                if let Some(login) = &mut self.login {
+                    let was_done = login.stage == LoginStage::Done;
+                    login.stage = login.stage.next(&event);
+
+                    if let LoginEvent::Error(e) = &event {
+                        let e = if login.auth_key {
+                            AuthKeyError::from_message(e).to_string()
+                        } else {
+                            e.clone()
+                        };
+                        login.error = Some(format!("{}: {e}", fl!("accounts-login-failed")));
+                    }
+
+                    // The bus replays a pending URL on reconnect; only open
+                    // a new one.
+                    if let LoginEvent::BrowseToUrl(url) = event
+                        && login.url.as_ref() != Some(&url)
+                    {
+                        login.open_error = open_url(&url).err().map(|e| e.to_string());
+                        login.qr = qr_code::Data::new(&url).ok();
+                        login.url = Some(url);
+                    }
+
+                    if login.stage == LoginStage::Done && !was_done {
+                        if login.auth_key {
+                            self.auth_key_form = None;
+                        }
+                        // Leave the success message up for a moment.
+                        tasks.push(task::future(async move {
+                            clear_status(STATUS_CLEAR_TIME).await;
+                            Message::LoginDismissed
+                        }));
+                        self.profile_name_draft = None;
+                        if self.preferences.notifications_enabled {
+                            self.notifications.login_succeeded();
+                        }
+                        // The new profile is now current; reload so the
+                        // account list selects it.
+                        tasks.push(task::future(async move { Message::IpnEvent }));
+                    }
+                }
+            }
+            Message::CancelLogin => {
+                self.login = None;
+            }
+            Message::LoginDismissed => {
+                // A login started since then stays up.
+                if self
+                    .login
+                    .as_ref()
+                    .is_some_and(|login| login.stage == LoginStage::Done)
+                {
+                    self.login = None;
+                }
+            }
+            Message::ToggleAuthKeyForm => {
+                self.auth_key_form = match self.auth_key_form {
+                    Some(_) => None,
+                    None => Some(AuthKeyForm::default()),
+                };
+            }
+            Message::AuthKeyInput(val) => {
+                if let Some(form) = &mut self.auth_key_form {
+                    form.auth_key = val;
+                }
+            }
+            Message::AuthKeyTagsInput(val) => {
+                if let Some(form) = &mut self.auth_key_form {
+                    form.tags = val;
+                }
+            }
+            Message::AuthKeyHostnameInput(val) => {
+                if let Some(form) = &mut self.auth_key_form {
+                    form.hostname = val;
+                }
+            }
+            Message::AuthKeyControlUrlInput(val) => {
+                if let Some(form) = &mut self.auth_key_form {
+                    form.control_url = val;
+                }
+            }
+            Message::SubmitAuthKey => {
+                if let Some(form) = &mut self.auth_key_form {
+                    let tags: Option<Vec<String>> = form
+                        .tags
+                        .split(',')
+                        .filter(|tag| !tag.trim().is_empty())
+                        .map(parse_tag)
+                        .collect();
+                    let hostname = form.hostname.trim().to_string();
+                    let control_url = form.control_url.trim().trim_end_matches('/').to_string();
+
+                    let Some(advertise_tags) = tags else {
+                        form.error = fl!("node-tag-invalid");
+                        return Task::none();
+                    };
+                    if !hostname.is_empty() && !valid_dns_label(&hostname) {
+                        form.error = fl!("hostname-invalid");
+                        return Task::none();
+                    }
+                    if !valid_control_url(&control_url) {
+                        form.error = fl!("advanced-control-url-invalid");
+                        return Task::none();
+                    }
+                    form.error.clear();
+
+                    let login = AuthKeyLogin {
+                        auth_key: form.auth_key.clone(),
+                        hostname,
+                        advertise_tags,
+                        control_url,
+                    };
+                    self.login = Some(LoginFlow {
+                        auth_key: true,
+                        ..Default::default()
+                    });
+                    let client = self.client.clone();
+                    tasks.push(task::future(async move {
+                        Message::AuthKeyLoginDone(login_with_auth_key(&client, &login).await)
+                    }));
+                }
+            }
+            Message::AuthKeyLoginDone(result) => {
+                if let Err(e) = result {
+                    self.login = None;
+                    if let Some(form) = &mut self.auth_key_form {
+                        form.error = e.to_string();
                     }
+                }
+            }
+            Message::LogoutAccount => {
+                let client = self.client.clone();
+                tasks.push(task::future(async move {
+                    Message::AccountActionDone(
+                        logout_account(&client).await.map_err(|e| e.to_string()),
+                    )
+                }));
+            }
+            Message::DeleteAccount(profile_id) => {
+                self.confirm_delete_account = Some(profile_id);
+            }
+            Message::CancelDeleteAccount => {
+                self.confirm_delete_account = None;
+            }
+            Message::ConfirmDeleteAccount => {
+                if let Some(profile_id) = self.confirm_delete_account.take() {
+                    let client = self.client.clone();
+                    tasks.push(task::future(async move {
+                        Message::AccountActionDone(
+                            delete_account(&client, &profile_id)
+                                .await
+                                .map_err(|e| e.to_string()),
+                        )
+                    }));
+                }
+            }
+            Message::AccountActionDone(result) => {
+                self.account_status = match result {
+                    Ok(()) => String::new(),
+                    Err(e) => e,
+                };
+                tasks.push(task::future(async move { Message::IpnEvent }));
+            }
+            Message::Reauthenticate => {
+                self.login = Some(LoginFlow::default());
+                let client = self.client.clone();
+                tasks.push(task::future(async move {
+                    Message::LoginStarted(
+                        reauthenticate(&client)
+                            .await
+                            .map_err(|e| format!("reauthenticate: {e}")),
+                    )
                 }));
             }
             Message::DeviceSelected(device) => {
@@ -471,6 +1240,7 @@ impl cosmic::Application for Window {
                     let files = self.send_files.clone();
                     let dev_name = self.selected_device_name.clone();
                     let notify = self.preferences.notifications_enabled;
+                    let notifications = self.notifications.clone();
 
                     // Find the peer ID for the selected device
                     let peer_id = self
@@ -486,7 +1256,7 @@ impl cosmic::Application for Window {
                     tasks.push(task::future(async move {
                         let result = send_files(&client, &peer_id, &files).await;
                         if notify && result.is_none() {
-                            notify_files_sent(&dev_name, file_count);
+                            notifications.files_sent(&dev_name, file_count);
                         }
                         Message::FilesSent(result)
                     }));
@@ -523,12 +1293,13 @@ impl cosmic::Application for Window {
 
                 let notify = self.preferences.notifications_enabled
                     && self.preferences.notify_on_incoming_files;
+                let notifications = self.notifications.clone();
 
                 tasks.push(task::future(async move {
                     match receive_files(&client, &download_dir).await {
                         Ok(names) => {
                             if notify {
-                                notify_files_received(&download_dir);
+                                notifications.files_received(&download_dir);
                             }
                             Message::FilesRecieved(format!(
                                 "Received {} file(s) in {download_dir}",
@@ -570,18 +1341,19 @@ impl cosmic::Application for Window {
                     self.sel_exit_node_idx = Some(exit_node);
                     let client = self.client.clone();
 
-                    let node_ip = if exit_node == 0 {
-                        String::new()
-                    } else {
-                        self.state
-                            .exit_node_options
-                            .get(exit_node - 1)
-                            .and_then(|dev| dev.tailscale_ips.first())
-                            .cloned()
-                            .unwrap_or_default()
-                    };
+                    let dev = exit_node
+                        .checked_sub(1)
+                        .and_then(|idx| self.state.exit_node_options.get(idx));
+                    let node_ip = dev
+                        .and_then(|dev| dev.tailscale_ips.first())
+                        .cloned()
+                        .unwrap_or_default();
+                    let name = dev.map(|dev| dev.name.clone());
 
-                    update_config(self.config.clone(), "exit-node", exit_node);
+                    self.save_pref(|prefs, config| {
+                        prefs.set_exit_node_idx(config, Some(exit_node))
+                    });
+                    self.save_pref(|prefs, config| prefs.set_exit_node(config, name));
 
                     tasks.push(task::future(async move {
                         let _ = set_exit_node(&client, &node_ip).await;
@@ -590,14 +1362,14 @@ impl cosmic::Application for Window {
                 }
             }
             Message::AllowExitNodeLanAccess(allow) => {
-                if self.state.is_exit_node {
-                    let client = self.client.clone();
-                    update_config(self.config.clone(), "allow-lan", allow);
-                    tasks.push(task::future(async move {
-                        let _ = set_exit_node_allow_lan(&client, allow).await;
-                        Message::ActionCompleted(Ok(()))
-                    }));
-                }
+                // LAN access applies while using an exit node, so it can be
+                // set whether or not this node advertises itself as one.
+                let client = self.client.clone();
+                self.save_pref(|prefs, config| prefs.set_allow_lan(config, allow));
+                tasks.push(task::future(async move {
+                    let _ = set_exit_node_allow_lan(&client, allow).await;
+                    Message::ActionCompleted(Ok(()))
+                }));
             }
             Message::UpdateIsExitNode(enable) => {
                 if self.sel_exit_node_idx == Some(0) || self.sel_exit_node_idx.is_none() {
@@ -629,23 +1401,164 @@ impl cosmic::Application for Window {
             Message::PingCompleted(result) => {
                 self.ping_in_progress = false;
                 self.ping_result = result.ok();
+
+                if let Some(ref pr) = self.ping_result
+                    && pr.err.is_empty()
+                {
+                    self.device_latencies
+                        .insert(pr.ip.clone(), pr.latency_seconds);
+                }
             }
             Message::CopyToClipboard(val) => {
                 let _ = copy_to_clipboard(&val);
             }
+            Message::OpenSsh(host, tailscale_ssh) => {
+                if let Err(e) = open_ssh_session(
+                    &self.preferences.terminal,
+                    &self.preferences.ssh_user,
+                    &host,
+                    tailscale_ssh,
+                ) {
+                    eprintln!("{e}");
+                }
+            }
+            Message::ToggleFavorite(id) => {
+                let mut favorites = self.preferences.favorites.clone();
+                if let Some(pos) = favorites.iter().position(|fav| *fav == id) {
+                    favorites.remove(pos);
+                } else {
+                    favorites.push(id);
+                }
+                self.save_pref(|prefs, config| prefs.set_favorites(config, favorites));
+            }
+            Message::QuickSend(id) => {
+                // Preselect the favorite as TailDrop target, then pick files.
+                if let Some(name) = self
+                    .state
+                    .devices
+                    .iter()
+                    .find(|dev| dev.id == id)
+                    .map(|dev| dev.name.clone())
+                    && let Some(idx) = self.state.device_names.iter().position(|n| *n == name)
+                {
+                    self.selected_device_idx = Some(idx);
+                    self.selected_device_name = name;
+                    self.files_sent = false;
+                    self.active_tab = Tab::TailDrop;
+                    tasks.push(task::future(async move { Message::ChooseFiles }));
+                }
+            }
+            Message::SetNotifyFavoritePresence(val) => {
+                self.save_pref(|prefs, config| prefs.set_notify_on_favorite_presence(config, val));
+            }
+            Message::ToggleWatch(id) => {
+                let mut watched_devices = self.preferences.watched_devices.clone();
+                if let Some(pos) = watched_devices.iter().position(|watched| *watched == id) {
+                    watched_devices.remove(pos);
+                } else {
+                    watched_devices.push(id);
+                }
+                self.save_pref(|prefs, config| prefs.set_watched_devices(config, watched_devices));
+            }
+            Message::PresenceTick => {
+                self.presence_deadline = None;
+                tasks.extend(self.settle_presence());
+            }
+            Message::DeviceSearch(query) => {
+                self.device_query = query;
+            }
+            Message::ToggleDeviceFilter(filter) => {
+                if let Some(pos) = self.device_filters.iter().position(|f| *f == filter) {
+                    self.device_filters.remove(pos);
+                } else {
+                    self.device_filters.push(filter);
+                }
+            }
+            Message::DeviceGroupingSelected(idx) => {
+                if let Some(grouping) = DeviceGrouping::ALL.get(idx) {
+                    self.device_grouping = *grouping;
+                }
+            }
+            Message::DeviceSortSelected(idx) => {
+                if let Some(sort) = DeviceSort::ALL.get(idx) {
+                    self.device_sort = *sort;
+                }
+            }
+            Message::OpenUrl(url) => {
+                if let Err(e) = open_url(&url) {
+                    eprintln!("Failed to open {url}: {e}");
+                }
+            }
+            Message::SignNode(node_key) => {
+                self.tka_status = String::new();
+                let client = self.client.clone();
+                tasks.push(task::future(async move {
+                    match sign_node(&client, &node_key).await {
+                        Ok(()) => Message::NodeSigned(Ok(())),
+                        Err(e) => Message::NodeSigned(Err(e.to_string())),
+                    }
+                }));
+            }
+            Message::NodeSigned(result) => {
+                self.tka_status = match result {
+                    Ok(()) => fl!("tka-signed-ok"),
+                    Err(e) => format!("{}: {e}", fl!("tka-sign-error")),
+                };
+                // Signing changes the filtered peer list, refresh it.
+                tasks.push(task::future(async move { Message::IpnEvent }));
+            }
+            Message::HostnameInput(val) => {
+                self.hostname_draft = Some(val);
+                self.hostname_status.clear();
+            }
+            Message::SaveHostname => {
+                if let Some(hostname) = &self.hostname_draft {
+                    let hostname = hostname.trim().to_string();
+                    // Empty resets to the OS hostname.
+                    if hostname.is_empty() || valid_dns_label(&hostname) {
+                        let client = self.client.clone();
+                        tasks.push(task::future(async move {
+                            Message::HostnameSaved(
+                                set_hostname(&client, &hostname)
+                                    .await
+                                    .map_err(|e| e.to_string()),
+                            )
+                        }));
+                    } else {
+                        self.hostname_status = fl!("hostname-invalid");
+                    }
+                }
+            }
+            Message::HostnameSaved(result) => match result {
+                Ok(()) => {
+                    self.hostname_draft = None;
+                    self.hostname_status.clear();
+                    tasks.push(task::future(async move { Message::IpnEvent }));
+                }
+                Err(e) => self.hostname_status = e,
+            },
             Message::SubnetInput(val) => {
                 self.subnet_input = val;
+                self.subnet_error.clear();
             }
             Message::AddSubnet => {
-                if !self.subnet_input.is_empty() {
-                    let mut routes = self.state.advertised_routes.clone();
-                    routes.push(self.subnet_input.clone());
-                    self.subnet_input.clear();
-                    let client = self.client.clone();
-                    tasks.push(task::future(async move {
-                        let _ = set_advertised_routes(&client, routes).await;
-                        Message::ActionCompleted(Ok(()))
-                    }));
+                match validate_new_route(&self.subnet_input, &self.state.advertised_routes) {
+                    Ok(cidr) => {
+                        let mut routes = self.state.advertised_routes.clone();
+                        routes.push(cidr.to_string());
+                        self.subnet_input.clear();
+                        self.subnet_error.clear();
+                        let client = self.client.clone();
+                        tasks.push(task::future(async move {
+                            match set_advertised_routes(&client, routes).await {
+                                Ok(()) => Message::ActionCompleted(Ok(())),
+                                Err(e) => Message::ActionCompleted(Err(format!(
+                                    "set_advertised_routes: {e}"
+                                ))),
+                            }
+                        }));
+                    }
+                    Err(e) => self.subnet_error = e.to_string(),
                 }
             }
             Message::RemoveSubnet(idx) => {
@@ -660,39 +1573,406 @@ impl cosmic::Application for Window {
                 }
             }
             Message::SetAutoConnect(val) => {
-                self.preferences.auto_connect = val;
-                update_config(self.config.clone(), "auto-connect", val);
+                self.save_pref(|prefs, config| prefs.set_auto_connect(config, val));
             }
             Message::SetNotificationsEnabled(val) => {
-                self.preferences.notifications_enabled = val;
-                update_config(self.config.clone(), "notifications-enabled", val);
+                self.save_pref(|prefs, config| prefs.set_notifications_enabled(config, val));
             }
             Message::SetNotifyConnection(val) => {
-                self.preferences.notify_on_connection_change = val;
-                update_config(self.config.clone(), "notify-connection", val);
+                self.save_pref(|prefs, config| prefs.set_notify_on_connection_change(config, val));
             }
             Message::SetNotifyFiles(val) => {
-                self.preferences.notify_on_incoming_files = val;
-                update_config(self.config.clone(), "notify-files", val);
+                self.save_pref(|prefs, config| prefs.set_notify_on_incoming_files(config, val));
             }
             Message::SetNotifyDevice(val) => {
-                self.preferences.notify_on_new_device = val;
-                update_config(self.config.clone(), "notify-device", val);
+                self.save_pref(|prefs, config| prefs.set_notify_on_new_device(config, val));
             }
-            Message::SetIconStyle(dynamic) => {
-                self.preferences.icon_style = if dynamic {
-                    "dynamic".to_string()
-                } else {
-                    "static".to_string()
-                };
-                update_config(
-                    self.config.clone(),
-                    "icon-style",
-                    self.preferences.icon_style.clone(),
-                );
+            Message::NetworkChanged(network) => {
+                if self.current_network == network {
+                    return Task::none();
+                }
+                self.current_network = network;
+                tasks.push(load_local_networks());
+                tasks.extend(self.apply_shields_rule());
+                if self.initial_load_done {
+                    tasks.extend(self.apply_network_rules());
+                }
             }
-            Message::ChooseDownloadDir => {
-                tasks.push(task::future(async move {
+            Message::LocalNetworksLoaded(networks) => {
+                self.local_networks = networks;
+            }
+            Message::Pause(duration) => {
+                let resume_at = parse_schedule_time(&self.preferences.schedule_connect)
+                    .unwrap_or(DEFAULT_RESUME_TIME);
+                let until = pause_until(duration, Local::now(), resume_at);
+                self.set_paused_until(Some(until.timestamp()));
+                let client = self.client.clone();
+                tasks.push(task::future(async move {
+                    match set_connected(&client, false).await {
+                        Ok(()) => Message::ActionCompleted(Ok(())),
+                        Err(e) => Message::ActionCompleted(Err(format!("set_connected: {e}"))),
+                    }
+                }));
+            }
+            Message::Resume => {
+                self.set_paused_until(None);
+                let client = self.client.clone();
+                tasks.push(task::future(async move {
+                    match set_connected(&client, true).await {
+                        Ok(()) => Message::ActionCompleted(Ok(())),
+                        Err(e) => Message::ActionCompleted(Err(format!("set_connected: {e}"))),
+                    }
+                }));
+            }
+            Message::ScheduleTick => {
+                let now = Local::now();
+                let since = std::mem::replace(&mut self.last_schedule_check, now);
+
+                // A pause that ended while the applet wasn't running is
+                // picked up on the first tick.
+                if self
+                    .preferences
+                    .paused_until
+                    .is_some_and(|until| until <= now.timestamp())
+                {
+                    tasks.push(task::future(async move { Message::Resume }));
+                } else if !self.is_paused() {
+                    let crossed = |time: &str| {
+                        parse_schedule_time(time).is_some_and(|at| schedule_crossed(at, since, now))
+                    };
+                    let connect = if crossed(&self.preferences.schedule_disconnect) {
+                        Some(false)
+                    } else if crossed(&self.preferences.schedule_connect) {
+                        Some(true)
+                    } else {
+                        None
+                    };
+                    if let Some(connect) = connect
+                        && connect != self.state.connected
+                    {
+                        let client = self.client.clone();
+                        tasks.push(task::future(async move {
+                            match set_connected(&client, connect).await {
+                                Ok(()) => Message::ActionCompleted(Ok(())),
+                                Err(e) => {
+                                    Message::ActionCompleted(Err(format!("set_connected: {e}")))
+                                }
+                            }
+                        }));
+                    }
+                }
+            }
+            Message::ScheduleConnectInput(val) => {
+                self.schedule_connect_input = val;
+                self.save_schedules();
+            }
+            Message::ScheduleDisconnectInput(val) => {
+                self.schedule_disconnect_input = val;
+                self.save_schedules();
+            }
+            Message::RuleMatchSelected(idx) => {
+                self.rule_draft.match_idx = idx;
+                // Prefill with the current network's value for that key.
+                let network = self.current_network.as_ref();
+                self.rule_draft.value = match idx {
+                    0 => network.and_then(|net| net.ssid.clone()),
+                    1 => network.and_then(|net| net.gateway_mac.clone()),
+                    2 => network.map(|net| net.interface.clone()),
+                    _ => None,
+                }
+                .unwrap_or_default();
+            }
+            Message::RuleValueInput(val) => {
+                self.rule_draft.value = val;
+            }
+            Message::RuleActionSelected(idx) => {
+                self.rule_draft.action_idx = idx;
+            }
+            Message::RuleExitNodeSelected(idx) => {
+                self.rule_draft.exit_node = match idx {
+                    0 => ExitNodeRule::Keep,
+                    1 => ExitNodeRule::Clear,
+                    idx => self
+                        .rule_exit_names
+                        .get(idx)
+                        .cloned()
+                        .map_or(ExitNodeRule::Keep, ExitNodeRule::Use),
+                };
+            }
+            Message::AddNetworkRule => {
+                let value = self.rule_draft.value.trim().to_string();
+                let matcher = match self.rule_draft.match_idx {
+                    0 => NetworkMatch::Ssid(value),
+                    1 => NetworkMatch::GatewayMac(value.to_lowercase()),
+                    2 => NetworkMatch::Interface(value),
+                    _ => NetworkMatch::UntrustedWifi,
+                };
+                let incomplete = matches!(
+                    &matcher,
+                    NetworkMatch::Ssid(v) | NetworkMatch::GatewayMac(v) | NetworkMatch::Interface(v)
+                        if v.is_empty()
+                );
+                if !incomplete {
+                    let action = if self.rule_draft.action_idx == 1 {
+                        RuleAction::Disconnect
+                    } else {
+                        RuleAction::Connect(self.rule_draft.exit_node.clone())
+                    };
+                    let mut rules = self.preferences.network_rules.clone();
+                    rules.push(NetworkRule { matcher, action });
+                    self.rule_draft = RuleDraft::default();
+                    self.save_pref(|prefs, config| prefs.set_network_rules(config, rules));
+                    tasks.extend(self.apply_network_rules());
+                }
+            }
+            Message::RemoveNetworkRule(idx) => {
+                if idx < self.preferences.network_rules.len() {
+                    let mut rules = self.preferences.network_rules.clone();
+                    rules.remove(idx);
+                    self.save_pref(|prefs, config| prefs.set_network_rules(config, rules));
+                }
+            }
+            Message::ToggleTrustedNetwork(name) => {
+                let mut trusted = self.preferences.trusted_networks.clone();
+                if let Some(pos) = trusted.iter().position(|net| *net == name) {
+                    trusted.remove(pos);
+                } else {
+                    trusted.push(name);
+                }
+                self.save_pref(|prefs, config| prefs.set_trusted_networks(config, trusted));
+                tasks.extend(self.apply_shields_rule());
+            }
+            Message::SetEnforcePrefs(val) => {
+                // Start from what the daemon has now, so turning this on
+                // changes nothing by itself.
+                if val {
+                    let state = &self.state;
+                    let (ssh, routes, magic_dns, allow_lan, exit_node) = (
+                        state.ssh_enabled,
+                        state.accept_routes,
+                        state.magic_dns,
+                        state.exit_node_allow_lan,
+                        current_exit_node(state),
+                    );
+                    self.save_pref(|prefs, config| prefs.set_ssh_enabled(config, ssh));
+                    self.save_pref(|prefs, config| prefs.set_routes_accepted(config, routes));
+                    self.save_pref(|prefs, config| prefs.set_magic_dns(config, magic_dns));
+                    self.save_pref(|prefs, config| prefs.set_allow_lan(config, allow_lan));
+                    self.save_pref(|prefs, config| prefs.set_exit_node(config, exit_node));
+                }
+                self.save_pref(|prefs, config| prefs.set_enforce_prefs(config, val));
+            }
+            Message::SetAutoShieldsUp(val) => {
+                self.save_pref(|prefs, config| prefs.set_auto_shields_up(config, val));
+                tasks.extend(self.apply_shields_rule());
+            }
+            Message::ConfigChanged(keys, prefs) => {
+                // Our own writes are echoed back too, possibly read before a
+                // later write. Only take keys changed elsewhere, e.g. by
+                // cosmic-settings or a text editor.
+                let now = Instant::now();
+                self.own_writes
+                    .retain(|_, written| now.duration_since(*written) < OWN_WRITE_ECHO);
+                let keys: Vec<String> = keys
+                    .into_iter()
+                    .filter(|key| !self.own_writes.contains_key(key))
+                    .collect();
+                let prefs = merge_keys(&self.preferences, &prefs, &keys);
+                if prefs != self.preferences {
+                    tasks.extend(self.replace_preferences(prefs));
+                }
+            }
+            Message::SetGlobalShortcuts(val) => match set_global_shortcuts(val) {
+                Ok(()) => {
+                    self.shortcuts_status.clear();
+                    self.save_pref(|prefs, config| prefs.set_global_shortcuts(config, val));
+                }
+                Err(e) => {
+                    self.shortcuts_status = format!("{}: {e}", fl!("shortcuts-failed"));
+                }
+            },
+            Message::SetNotifyKeyExpiry(val) => {
+                self.save_pref(|prefs, config| prefs.set_notify_on_key_expiry(config, val));
+            }
+            Message::ToggleNotificationStyles => {
+                self.show_notification_styles = !self.show_notification_styles;
+            }
+            Message::NotificationIconInput(kind, icon) => {
+                self.edit_notification_style(kind, |style| style.icon = icon);
+            }
+            Message::NotificationSoundInput(kind, sound) => {
+                self.edit_notification_style(kind, |style| style.sound = sound);
+            }
+            Message::PollIntervalInput(val) => {
+                if let Ok(secs) = val.trim().parse::<u64>()
+                    && (1..=MAX_POLL_INTERVAL_SECS).contains(&secs)
+                {
+                    self.save_pref(|prefs, config| prefs.set_poll_interval_secs(config, secs));
+                }
+                self.poll_interval_input = val;
+            }
+            Message::KeyExpiryRemindersInput(val) => {
+                self.key_expiry_input = val;
+                tasks.push(self.schedule_input_save());
+            }
+            Message::SetIconStyle(dynamic) => {
+                let icon_style = if dynamic { "dynamic" } else { "static" };
+                self.save_pref(|prefs, config| {
+                    prefs.set_icon_style(config, icon_style.to_string())
+                });
+            }
+            Message::TerminalInput(val) => {
+                self.terminal_input = val;
+                tasks.push(self.schedule_input_save());
+            }
+            Message::SshUserInput(val) => {
+                self.ssh_user_input = val;
+                tasks.push(self.schedule_input_save());
+            }
+            Message::PresenceDebounceInput(val) => {
+                self.presence_debounce_input = val;
+                tasks.push(self.schedule_input_save());
+            }
+            Message::SaveInputs(seq) => {
+                // Typing since this save was scheduled queued another one.
+                if seq == self.input_save_seq {
+                    self.save_inputs();
+                }
+            }
+            Message::TagInput(val) => {
+                self.tag_input = val;
+                self.node_prefs_status.clear();
+            }
+            Message::AddTag => match parse_tag(&self.tag_input) {
+                Some(tag) if self.state.advertised_tags.contains(&tag) => {
+                    self.tag_input.clear();
+                }
+                Some(tag) => {
+                    let mut tags = self.state.advertised_tags.clone();
+                    tags.push(tag);
+                    self.tag_input.clear();
+                    let client = self.client.clone();
+                    tasks.push(task::future(async move {
+                        Message::NodePrefsSet(
+                            set_advertise_tags(&client, tags)
+                                .await
+                                .map_err(|e| e.to_string()),
+                        )
+                    }));
+                }
+                None => self.node_prefs_status = fl!("node-tag-invalid"),
+            },
+            Message::RemoveTag(idx) => {
+                if idx < self.state.advertised_tags.len() {
+                    let mut tags = self.state.advertised_tags.clone();
+                    tags.remove(idx);
+                    let client = self.client.clone();
+                    tasks.push(task::future(async move {
+                        Message::NodePrefsSet(
+                            set_advertise_tags(&client, tags)
+                                .await
+                                .map_err(|e| e.to_string()),
+                        )
+                    }));
+                }
+            }
+            Message::SetAppConnector(val) => {
+                let client = self.client.clone();
+                tasks.push(task::future(async move {
+                    Message::NodePrefsSet(
+                        set_app_connector(&client, val)
+                            .await
+                            .map_err(|e| e.to_string()),
+                    )
+                }));
+            }
+            Message::NodePrefsSet(result) => {
+                // Daemon errors such as a tag the ACL doesn't permit stay
+                // visible until the next edit.
+                self.node_prefs_status = match result {
+                    Ok(()) => String::new(),
+                    Err(e) => e,
+                };
+                tasks.push(task::future(async move { Message::IpnEvent }));
+            }
+            Message::ToggleAdvanced => {
+                self.show_advanced = !self.show_advanced;
+            }
+            Message::SetPref(edit) => {
+                self.advanced_status.clear();
+                let client = self.client.clone();
+                tasks.push(task::future(async move {
+                    Message::PrefSet(set_pref(&client, edit).await.map_err(|e| e.to_string()))
+                }));
+            }
+            Message::PrefSet(result) => {
+                self.advanced_status = match result {
+                    Ok(()) => String::new(),
+                    Err(e) => e,
+                };
+                tasks.push(task::future(async move { Message::IpnEvent }));
+            }
+            Message::NetfilterModeSelected(idx) => {
+                if let Some(mode) = NetfilterMode::ALL.get(idx) {
+                    let edit = PrefEdit::NetfilterMode(*mode);
+                    tasks.push(task::future(async move { Message::SetPref(edit) }));
+                }
+            }
+            Message::OperatorUserInput(val) => {
+                self.operator_user_draft = Some(val);
+            }
+            Message::ApplyOperatorUser => {
+                if let Some(user) = self.operator_user_draft.take() {
+                    let user = user.trim().to_string();
+                    // An empty operator clears it.
+                    if user.is_empty() || valid_operator_user(&user) {
+                        let edit = PrefEdit::OperatorUser(user);
+                        tasks.push(task::future(async move { Message::SetPref(edit) }));
+                    } else {
+                        self.advanced_status = fl!("advanced-operator-invalid");
+                        self.operator_user_draft = Some(user);
+                    }
+                }
+            }
+            Message::ControlUrlInput(val) => {
+                self.control_url_draft = Some(val);
+            }
+            Message::ApplyControlUrl => {
+                if let Some(url) = self.control_url_draft.take() {
+                    let url = url.trim().trim_end_matches('/').to_string();
+                    if valid_control_url(&url) {
+                        let edit = PrefEdit::ControlUrl(url);
+                        tasks.push(task::future(async move { Message::SetPref(edit) }));
+                    } else {
+                        self.advanced_status = fl!("advanced-control-url-invalid");
+                        self.control_url_draft = Some(url);
+                    }
+                }
+            }
+            Message::ProfileNameInput(val) => {
+                self.profile_name_draft = Some(val);
+            }
+            Message::ApplyProfileName => {
+                if let Some(name) = self.profile_name_draft.take() {
+                    let name = name.trim().to_string();
+                    if name.is_empty() {
+                        self.account_status = fl!("advanced-profile-name-empty");
+                    } else {
+                        // Reported with the other account actions, next to
+                        // the field, not in the collapsed Advanced section.
+                        self.account_status.clear();
+                        let client = self.client.clone();
+                        let edit = PrefEdit::ProfileName(name);
+                        tasks.push(task::future(async move {
+                            Message::AccountActionDone(
+                                set_pref(&client, edit).await.map_err(|e| e.to_string()),
+                            )
+                        }));
+                    }
+                }
+            }
+            Message::ChooseDownloadDir => {
+                tasks.push(task::future(async move {
                     let title = fl!("dir-chooser-title");
                     let dialog = file_chooser::open::Dialog::new().title(title);
                     match dialog.open_folders().await {
@@ -708,12 +1988,80 @@ impl cosmic::Application for Window {
                     .and_then(|url| url.to_file_path().ok())
                     .and_then(|p| p.to_str().map(str::to_string))
                 {
-                    self.preferences.download_dir = Some(path.clone());
-                    update_config(self.config.clone(), "download-dir", path);
+                    self.save_pref(|prefs, config| prefs.set_download_dir(config, Some(path)));
                 }
                 return self.reopen_popup();
             }
             Message::DownloadDirCancelled => return self.reopen_popup(),
+            Message::ExportSettings => {
+                tasks.push(task::future(async move {
+                    let dialog = file_chooser::save::Dialog::new()
+                        .title(fl!("settings-export-title"))
+                        .file_name(SETTINGS_FILE_NAME);
+                    match dialog.save_file().await {
+                        Ok(response) => match response.url() {
+                            Some(url) => Message::ExportPathSelected(url.clone()),
+                            None => Message::SettingsFileCancelled,
+                        },
+                        Err(file_chooser::Error::Cancelled) => Message::SettingsFileCancelled,
+                        Err(e) => {
+                            eprintln!("Choosing the export file went wrong: {e}");
+                            Message::SettingsFileCancelled
+                        }
+                    }
+                }));
+            }
+            Message::ExportPathSelected(url) => {
+                let result = url
+                    .to_file_path()
+                    .map_err(|()| format!("not a local file: {url}"))
+                    .and_then(|path| export_preferences(&self.preferences, &path));
+                self.settings_file_status = match result {
+                    Ok(()) => fl!("settings-exported"),
+                    Err(e) => format!("{}: {e}", fl!("settings-export-failed")),
+                };
+                return self.reopen_popup();
+            }
+            Message::ImportSettings => {
+                tasks.push(task::future(async move {
+                    let dialog = file_chooser::open::Dialog::new()
+                        .title(fl!("settings-import-title"))
+                        .filter(FileFilter::new("JSON").glob("*.json"));
+                    match dialog.open_files().await {
+                        Ok(response) => match response.urls().first() {
+                            Some(url) => Message::ImportPathSelected(url.clone()),
+                            None => Message::SettingsFileCancelled,
+                        },
+                        Err(file_chooser::Error::Cancelled) => Message::SettingsFileCancelled,
+                        Err(e) => {
+                            eprintln!("Choosing the import file went wrong: {e}");
+                            Message::SettingsFileCancelled
+                        }
+                    }
+                }));
+            }
+            Message::ImportPathSelected(url) => {
+                let result = url
+                    .to_file_path()
+                    .map_err(|()| format!("not a local file: {url}"))
+                    .and_then(|path| import_preferences(&path));
+                match result {
+                    Ok(prefs) => {
+                        self.note_own_writes(changed_keys(&self.preferences, &prefs));
+                        if let Err(e) = prefs.write_entry(&self.config) {
+                            eprintln!("Failed to save imported preferences: {e}");
+                        }
+                        tasks.extend(self.replace_preferences(prefs));
+                        self.settings_file_status = fl!("settings-imported");
+                    }
+                    Err(e) => {
+                        self.settings_file_status =
+                            format!("{}: {e}", fl!("settings-import-failed"));
+                    }
+                }
+                tasks.push(self.reopen_popup());
+            }
+            Message::SettingsFileCancelled => return self.reopen_popup(),
             Message::ActionCompleted(result) => {
                 if let Err(e) = result {
                     eprintln!("Tailscale action failed: {e}");
@@ -729,20 +2077,35 @@ impl cosmic::Application for Window {
 
     // Libcosmic's view function
     fn view(&self) -> Element<'_, Self::Message> {
-        self.core
-            .applet
-            // Set the icon button to the Tailscale icon (labeled as flatpak name) defined during installation.
-            .icon_button("com.bhh32.gui-scale-applet")
-            .on_press(Message::TogglePopup)
-            .into()
+        // Right-click opens the account quick-switch menu.
+        mouse_area(
+            self.core
+                .applet
+                // The Tailscale icon (labeled as flatpak name) defined during installation, or a
+                // status icon; see panel_icon.
+                .icon_button(self.panel_icon())
+                .on_press(Message::TogglePopup),
+        )
+        .on_right_press(Message::ToggleQuickSwitch)
+        .into()
     }
 
     // Libcosmic's applet view_window function
     fn view_window(&self, _id: Id) -> Element<'_, Self::Message> {
+        if self.popup_kind == PopupKind::QuickSwitch {
+            return self
+                .core
+                .applet
+                .popup_container(self.view_quick_switch())
+                .into();
+        }
+
         let tab_bar = row![
             tab_button("network-vpn-symbolic", Tab::Status, self.active_tab),
             tab_button("send-to-symbolic", Tab::TailDrop, self.active_tab),
             tab_button("computer-symbolic", Tab::Devices, self.active_tab),
+            tab_button("network-wired-symbolic", Tab::Routes, self.active_tab),
+            tab_button("security-high-symbolic", Tab::TailnetLock, self.active_tab),
             tab_button(
                 "preferences-system-symbolic",
                 Tab::Settings,
@@ -756,10 +2119,16 @@ impl cosmic::Application for Window {
             Tab::Status => self.view_status_tab(),
             Tab::TailDrop => self.view_taildrop_tab(),
             Tab::Devices => self.view_devices_tab(),
+            Tab::Routes => self.view_routes_tab(),
+            Tab::TailnetLock => self.view_tailnet_lock_tab(),
             Tab::Settings => self.view_settings_tab(),
         };
 
-        let body = column![tab_bar, scrollable(content)].spacing(4);
+        let mut body = column![tab_bar].spacing(4);
+        if let Some((_, flash)) = &self.flash {
+            body = body.push(container(text(flash).size(12)).padding([0, 8]));
+        }
+        let body = body.push(scrollable(content));
         self.core.applet.popup_container(body).into()
     }
 }
@@ -773,6 +2142,113 @@ fn detail_row(label: String, value: String) -> Element<'static, Message> {
     .into()
 }
 
+/// "When <match>: <action>" summary of a network rule.
+fn network_rule_label(rule: &NetworkRule) -> String {
+    let matcher = match &rule.matcher {
+        NetworkMatch::Ssid(ssid) => format!("{} {ssid}", fl!("rules-match-ssid")),
+        NetworkMatch::GatewayMac(mac) => format!("{} {mac}", fl!("rules-match-gateway")),
+        NetworkMatch::Interface(iface) => format!("{} {iface}", fl!("rules-match-interface")),
+        NetworkMatch::UntrustedWifi => fl!("rules-match-untrusted"),
+    };
+    let action = match &rule.action {
+        RuleAction::Disconnect => fl!("rules-disconnect"),
+        RuleAction::Connect(ExitNodeRule::Keep) => fl!("rules-connect"),
+        RuleAction::Connect(ExitNodeRule::Clear) => {
+            format!("{}, {}", fl!("rules-connect"), fl!("rules-exit-none"))
+        }
+        RuleAction::Connect(ExitNodeRule::Use(name)) => {
+            format!("{}, {} {name}", fl!("rules-connect"), fl!("rules-exit-use"))
+        }
+    };
+    format!("{matcher}: {action}")
+}
+
+fn peer_route_status(status: PeerRouteStatus) -> String {
+    match status {
+        PeerRouteStatus::Active => fl!("routes-active"),
+        PeerRouteStatus::Standby => fl!("routes-standby"),
+        PeerRouteStatus::NotAccepted => fl!("routes-not-accepted"),
+        PeerRouteStatus::Offline => fl!("routes-offline"),
+    }
+}
+
+/// One-line summary of a peer route for the device details.
+fn peer_route_label(peer_route: &PeerRoute) -> String {
+    let mut label = format!(
+        "{} ({})",
+        peer_route.route,
+        peer_route_status(peer_route.status)
+    );
+    if !peer_route.lan_conflicts.is_empty() {
+        label.push_str(&format!(" ⚠ {}", fl!("routes-lan-conflict")));
+    }
+    label
+}
+
+/// A key expiry relative to `now`, e.g. "2024-05-01 (in 12 days)". Days are
+/// counted as for the expiry reminders.
+fn format_key_expiry(expiry: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let date = expiry.format("%Y-%m-%d").to_string();
+    if expiry <= now {
+        fl!("devices-key-expired-on", date = date)
+    } else {
+        fl!(
+            "devices-key-expires-on",
+            date = date,
+            days = days_until(expiry, now)
+        )
+    }
+}
+
+/// Read this host's on-link networks off the UI thread. They only change
+/// with the network, so they are reloaded when it does.
+fn load_local_networks() -> Task<Action<Message>> {
+    task::future(async move {
+        let networks = tokio::task::spawn_blocking(local_networks)
+            .await
+            .unwrap_or_default();
+        Message::LocalNetworksLoaded(networks)
+    })
+}
+
+/// Name of the peer in use as exit node.
+fn current_exit_node(state: &TailscaleState) -> Option<String> {
+    state
+        .devices
+        .iter()
+        .find(|dev| dev.is_exit_node && !dev.is_self)
+        .map(|dev| dev.name.clone())
+}
+
+fn notification_kind_label(kind: NotificationKind) -> String {
+    match kind {
+        NotificationKind::Connection => fl!("notification-kind-connection"),
+        NotificationKind::NewDevice => fl!("notification-kind-new-device"),
+        NotificationKind::Presence => fl!("notification-kind-presence"),
+        NotificationKind::IncomingFiles => fl!("notification-kind-incoming-files"),
+        NotificationKind::FilesSent => fl!("notification-kind-files-sent"),
+        NotificationKind::FilesReceived => fl!("notification-kind-files-received"),
+        NotificationKind::Login => fl!("notification-kind-login"),
+        NotificationKind::AccountSwitched => fl!("notification-kind-account-switched"),
+        NotificationKind::KeyExpiry => fl!("notification-kind-key-expiry"),
+        NotificationKind::PrefsRestored => fl!("notification-kind-prefs-restored"),
+    }
+}
+
+/// Short banner text for changes worth flashing in the open popup.
+fn flash_text(change: &StateChange) -> Option<String> {
+    match change {
+        StateChange::ExitNodeChanged { to: Some(name), .. } => {
+            Some(format!("{}: {name}", fl!("flash-exit-node")))
+        }
+        StateChange::ExitNodeChanged { to: None, .. } => Some(fl!("flash-exit-node-off")),
+        StateChange::AccountSwitched { to, .. } => Some(format!("{}: {to}", fl!("flash-account"))),
+        StateChange::PrefChanged(_) => Some(fl!("flash-prefs")),
+        StateChange::FilesArrived(_) => Some(fl!("flash-files")),
+        _ => None,
+    }
+}
+
 fn tab_button(icon_name: &'static str, tab: Tab, active: Tab) -> Element<'static, Message> {
     let btn = button::icon(icon::from_name(icon_name)).on_press(Message::TabSelected(tab));
     let btn = if tab == active {
@@ -802,6 +2278,90 @@ impl Window {
         get_popup(popup_settings)
     }
 
+    /// Node IDs whose presence is watched: the watch list, plus favorites
+    /// when favorite notifications are on.
+    fn watched_ids(&self) -> Vec<String> {
+        let mut ids = self.preferences.watched_devices.clone();
+        if self.preferences.notify_on_favorite_presence {
+            for id in &self.preferences.favorites {
+                if !ids.contains(id) {
+                    ids.push(id.clone());
+                }
+            }
+        }
+        ids
+    }
+
+    /// Notify presence changes that outlasted the debounce period and, while
+    /// others are still pending, schedule another check.
+    fn settle_presence(&mut self) -> Option<Task<Action<Message>>> {
+        let debounce = Duration::from_secs(self.preferences.presence_debounce_secs);
+        let changes = self.presence_watch.settle(Instant::now(), debounce);
+
+        if self.preferences.notifications_enabled {
+            for (id, change) in changes {
+                // Favorites not on the watch list only report on/offline.
+                if !self.preferences.watched_devices.contains(&id)
+                    && !matches!(change, PresenceChange::Online | PresenceChange::Offline)
+                {
+                    continue;
+                }
+                if let Some(dev) = self.state.devices.iter().find(|dev| dev.id == id) {
+                    self.notifications.presence_change(&dev.name, &change);
+                }
+            }
+        }
+
+        // Changes observed later fall due later, so the outstanding tick
+        // settles or reschedules them.
+        if self.presence_deadline.is_some() {
+            return None;
+        }
+        let deadline = self.presence_watch.next_due(debounce)?;
+        self.presence_deadline = Some(deadline);
+        Some(task::future(async move {
+            tokio::time::sleep_until(deadline.into()).await;
+            Message::PresenceTick
+        }))
+    }
+
+    /// Send the desktop notifications the user opted into for `diff`.
+    fn notify_state_changes(&self, diff: &StateDiff) {
+        let prefs = &self.preferences;
+        for change in &diff.changes {
+            match change {
+                StateChange::Connected | StateChange::Disconnected
+                    if prefs.notify_on_connection_change =>
+                {
+                    self.notifications
+                        .connection_change(*change == StateChange::Connected);
+                }
+                StateChange::DeviceAdded { name, .. } if prefs.notify_on_new_device => {
+                    self.notifications.new_device(name);
+                }
+                StateChange::AccountSwitched { to, .. } => {
+                    self.notifications.account_switched(to);
+                }
+                StateChange::FilesArrived(files) if prefs.notify_on_incoming_files => {
+                    self.notifications.incoming_files(files.len());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Push the current state to the D-Bus interface, if it is being served.
+    fn publish_dbus_state(&self) -> Option<Task<Action<Message>>> {
+        let conn = self.dbus.clone()?;
+        let dbus_state = DbusState::from(&self.state);
+        Some(task::future(async move {
+            match publish_state(&conn, dbus_state).await {
+                Ok(()) => Message::ActionCompleted(Ok(())),
+                Err(e) => Message::ActionCompleted(Err(format!("publish_state: {e}"))),
+            }
+        }))
+    }
+
     fn view_status_tab(&self) -> Element<'_, Message> {
         let state = &self.state;
         let sel_acct_idx = state.accounts.iter().position(|acct| acct.is_current);
@@ -814,18 +2374,28 @@ impl Window {
 
         let status_elements = list_column()
             .list_item_padding(5)
+            .add(settings::item(
+                fl!("status-shields-up"),
+                self.view_shields(),
+            ))
             .add(settings::item(
                 fl!("status-account"),
                 column![
-                    container(
+                    row![
                         button::standard(fl!("status-new-login"))
-                            .on_press(Message::LoginNewAccount)
-                    ),
+                            .on_press(Message::LoginNewAccount),
+                        button::standard(fl!("accounts-logout")).on_press(Message::LogoutAccount),
+                    ]
+                    .spacing(8),
                     dropdown(&self.acct_names, sel_acct_idx, Message::SwitchAccount),
                 ]
                 .spacing(8)
                 .align_x(Alignment::End),
             ))
+            .add(settings::item(
+                fl!("status-hostname"),
+                self.view_hostname_editor(),
+            ))
             .add(settings::item(
                 fl!("status-ipv4"),
                 row![
@@ -887,8 +2457,12 @@ impl Window {
             ))
             .add(settings::item(
                 fl!("status-connect-toggle"),
-                container(toggler(state.connected).on_toggle(Message::ConnectDisconnect))
-                    .align_x(Alignment::End),
+                column![
+                    toggler(state.connected).on_toggle(Message::ConnectDisconnect),
+                    self.view_pause(),
+                ]
+                .spacing(4)
+                .align_x(Alignment::End),
             ));
 
         // Subnet routes section
@@ -900,9 +2474,15 @@ impl Window {
             subnets_section = subnets_section.push(text(fl!("subnets-no-routes")).size(12));
         } else {
             for (idx, route) in state.advertised_routes.iter().enumerate() {
+                let approval = if state.approved_routes.contains(route) {
+                    fl!("subnets-approved")
+                } else {
+                    fl!("subnets-pending")
+                };
                 subnets_section = subnets_section.push(
                     row![
                         text(route).width(Length::Fill),
+                        text(approval).size(11),
                         button::destructive(fl!("subnets-remove"))
                             .on_press(Message::RemoveSubnet(idx))
                             .width(Length::Shrink),
@@ -910,6 +2490,9 @@ impl Window {
                     .spacing(8)
                     .align_y(Alignment::Center),
                 );
+                if let Ok(cidr) = route.parse::<Cidr>() {
+                    subnets_section = subnets_section.push(self.view_route_overlaps(&cidr));
+                }
             }
         }
 
@@ -926,8 +2509,18 @@ impl Window {
             .align_y(Alignment::Center),
         );
 
+        // Validate as the user types: show the error on submit, but warn
+        // about overlaps before the route is added.
+        if !self.subnet_error.is_empty() {
+            subnets_section = subnets_section.push(text(&self.subnet_error).size(11));
+        } else if let Ok(cidr) = self.subnet_input.parse::<Cidr>() {
+            subnets_section = subnets_section.push(self.view_route_overlaps(&cidr));
+        }
+
         Element::from(
             column![
+                self.view_login(),
+                self.view_favorites(),
                 Element::from(status_elements),
                 Element::from(subnets_section)
             ]
@@ -936,40 +2529,955 @@ impl Window {
         )
     }
 
-    fn view_taildrop_tab(&self) -> Element<'_, Message> {
-        // --- Send section ---
-        let send_header = text(fl!("taildrop-send-title")).size(14);
+    /// Panel icon. Shields-up is always indicated; the dynamic style also
+    /// shows when Tailscale is disconnected.
+    fn panel_icon(&self) -> &'static str {
+        if self.state.prefs.shields_up {
+            "security-high-symbolic"
+        } else if self.preferences.icon_style == "dynamic" && !self.state.connected {
+            "network-vpn-disconnected-symbolic"
+        } else {
+            "com.bhh32.gui-scale-applet"
+        }
+    }
 
-        let device_picker = row![
-            text(fl!("taildrop-send-to")).width(Length::Shrink),
-            dropdown(
-                &self.state.device_names,
-                self.selected_device_idx,
-                Message::DeviceSelected,
-            ),
-        ]
-        .spacing(8)
-        .align_y(Alignment::Center);
+    /// Progress of an interactive login: the auth link with a QR code for
+    /// signing in from a phone, then device approval.
+    fn view_login(&self) -> Element<'_, Message> {
+        let Some(login) = &self.login else {
+            return column![].into();
+        };
 
-        let mut send_section = column![send_header, device_picker].spacing(6).padding(4);
+        let title = match login.stage {
+            LoginStage::Starting => fl!("login-starting"),
+            LoginStage::NeedsLogin => fl!("login-needs-login"),
+            LoginStage::NeedsMachineAuth => fl!("login-needs-approval"),
+            LoginStage::Done => fl!("login-done"),
+        };
+        let mut col = column![text(title).size(14)].spacing(8);
 
-        if !self.send_files.is_empty() {
-            let mut files_col = column![text(fl!("taildrop-files-queued")).size(12)].spacing(2);
-            for path in &self.send_files {
-                files_col = files_col.push(text(path.clone()).size(11));
+        if let Some(url) = &login.url
+            && login.stage == LoginStage::NeedsLogin
+        {
+            col = col.push(
+                row![
+                    text(url).size(11),
+                    button::icon(icon::from_name("edit-copy-symbolic"))
+                        .on_press(Message::CopyToClipboard(url.clone()))
+                        .tooltip(fl!("copy-tooltip")),
+                    button::standard(fl!("login-open-browser"))
+                        .on_press(Message::OpenUrl(url.clone())),
+                ]
+                .spacing(8)
+                .align_y(Alignment::Center),
+            );
+            if let Some(qr) = &login.qr {
+                col = col.push(qr_code(qr).cell_size(4));
             }
-            send_section = send_section.push(files_col);
         }
-
-        let send_buttons = row![
-            button::standard(fl!("taildrop-choose-files")).on_press(Message::ChooseFiles),
-            button::suggested(fl!("taildrop-send")).on_press(Message::SendFiles),
-        ]
-        .spacing(8);
-        send_section = send_section.push(send_buttons);
-
-        if !self.send_file_status.is_empty() {
-            send_section = send_section.push(text(self.send_file_status.clone()).size(11));
+        if let Some(e) = &login.open_error {
+            col = col.push(text(format!("{}: {e}", fl!("login-open-failed"))).size(11));
+        }
+        if let Some(e) = &login.error {
+            col = col.push(text(e).size(11));
+        }
+
+        if login.stage != LoginStage::Done {
+            col = col.push(button::text(fl!("accounts-cancel")).on_press(Message::CancelLogin));
+        }
+        col.into()
+    }
+
+    /// Saved accounts with rename and delete, and login to a custom control
+    /// server.
+    fn view_accounts(&self) -> Element<'_, Message> {
+        let mut accounts = list_column().list_item_padding(5);
+        for acct in &self.state.accounts {
+            let label = if acct.tailnet.is_empty() {
+                acct.name.clone()
+            } else {
+                format!("{} ({})", acct.name, acct.tailnet)
+            };
+            let actions: Element<'_, Message> = if acct.is_current {
+                text(fl!("accounts-current")).size(11).into()
+            } else if self.confirm_delete_account.as_ref() == Some(&acct.id) {
+                row![
+                    button::destructive(fl!("accounts-delete-confirm"))
+                        .on_press(Message::ConfirmDeleteAccount),
+                    button::text(fl!("accounts-cancel")).on_press(Message::CancelDeleteAccount),
+                ]
+                .spacing(8)
+                .into()
+            } else {
+                button::destructive(fl!("accounts-delete"))
+                    .on_press(Message::DeleteAccount(acct.id.clone()))
+                    .into()
+            };
+            accounts = accounts.add(settings::item(label, actions));
+        }
+
+        let profile_name = self
+            .profile_name_draft
+            .clone()
+            .unwrap_or_else(|| self.state.prefs.profile_name.clone());
+        accounts = accounts
+            .add(settings::item(
+                fl!("accounts-rename"),
+                row![
+                    text_input("", profile_name)
+                        .on_input(Message::ProfileNameInput)
+                        .width(200),
+                    button::standard(fl!("advanced-apply")).on_press(Message::ApplyProfileName),
+                ]
+                .spacing(8)
+                .align_y(Alignment::Center),
+            ))
+            .add(settings::item(
+                fl!("accounts-control-url"),
+                row![
+                    text_input(
+                        "https://controlplane.tailscale.com",
+                        &self.login_control_url
+                    )
+                    .on_input(Message::LoginControlUrlInput)
+                    .width(200),
+                    button::suggested(fl!("status-new-login")).on_press(Message::LoginNewAccount),
+                ]
+                .spacing(8)
+                .align_y(Alignment::Center),
+            ));
+
+        let mut col = column![text(fl!("accounts-title")).size(14), accounts].spacing(4);
+        if !self.account_status.is_empty() {
+            col = col.push(text(&self.account_status).size(11));
+        }
+        col = col.push(button::text(fl!("auth-key-title")).on_press(Message::ToggleAuthKeyForm));
+        if let Some(form) = &self.auth_key_form {
+            col = col.push(self.view_auth_key_form(form));
+        }
+        col.into()
+    }
+
+    /// Open the popup as `kind`, or close whichever popup is open.
+    fn toggle_popup(&mut self, kind: PopupKind) -> Task<Action<Message>> {
+        if let Some(p) = self.popup.take() {
+            self.receive_file_status = String::new();
+            return destroy_popup(p);
+        }
+
+        let new_id = Id::unique();
+        self.popup.replace(new_id);
+        self.popup_kind = kind;
+
+        let mut popup_settings = self.core.applet.get_popup_settings(
+            self.core.main_window_id().unwrap(),
+            new_id,
+            None,
+            None,
+            None,
+        );
+
+        popup_settings.positioner.size_limits = match kind {
+            PopupKind::Full => Limits::NONE
+                .max_width(POPUP_MAX_WIDTH)
+                .min_width(POPUP_MIN_WIDTH)
+                .min_height(POPUP_MIN_HEIGHT)
+                .max_height(POPUP_MAX_HEIGHT),
+            PopupKind::QuickSwitch => Limits::NONE
+                .max_width(QUICK_SWITCH_MAX_WIDTH)
+                .min_width(QUICK_SWITCH_MIN_WIDTH)
+                .max_height(POPUP_MAX_HEIGHT),
+        };
+
+        get_popup(popup_settings)
+    }
+
+    /// Saved accounts with their tailnets; pressing one switches to it.
+    fn view_quick_switch(&self) -> Element<'_, Message> {
+        if self.state.accounts.is_empty() {
+            return container(text(fl!("quick-switch-none"))).padding(8).into();
+        }
+
+        let mut col = column![].spacing(4).padding(8);
+        for (idx, acct) in self.state.accounts.iter().enumerate() {
+            let mut label = column![text(&acct.name)];
+            if !acct.tailnet.is_empty() {
+                label = label.push(text(&acct.tailnet).size(11));
+            }
+            let check: Element<'_, Message> = if acct.is_current {
+                icon::from_name("object-select-symbolic").size(16).into()
+            } else {
+                container(text("")).width(16).into()
+            };
+            col = col.push(
+                button::custom(row![check, label].spacing(8).align_y(Alignment::Center))
+                    .class(cosmic::theme::Button::MenuItem)
+                    .width(Length::Fill)
+                    .on_press(Message::QuickSwitchAccount(idx)),
+            );
+        }
+        col.into()
+    }
+
+    /// Opt-in global shortcuts, registered in COSMIC's shortcut settings.
+    fn view_shortcuts(&self) -> Element<'_, Message> {
+        let mut elements = list_column().list_item_padding(5).add(settings::item(
+            fl!("shortcuts-enable"),
+            toggler(self.preferences.global_shortcuts).on_toggle(Message::SetGlobalShortcuts),
+        ));
+        for (keys, description) in shortcut_labels() {
+            elements = elements.add(settings::item(description, text(keys).size(12)));
+        }
+
+        let mut col = column![text(fl!("shortcuts-title")).size(14), elements].spacing(4);
+        if !self.shortcuts_status.is_empty() {
+            col = col.push(text(&self.shortcuts_status).size(11));
+        }
+        col.into()
+    }
+
+    /// Export and import of the applet's preferences.
+    fn view_settings_file(&self) -> Element<'_, Message> {
+        let buttons = row![
+            button::standard(fl!("settings-export")).on_press(Message::ExportSettings),
+            button::standard(fl!("settings-import")).on_press(Message::ImportSettings),
+        ]
+        .spacing(8);
+
+        let mut col = column![text(fl!("settings-file-title")).size(14), buttons].spacing(4);
+        if !self.settings_file_status.is_empty() {
+            col = col.push(text(&self.settings_file_status).size(11));
+        }
+        col.into()
+    }
+
+    /// Pre-auth key login: the key, plus optional tags, hostname and control
+    /// server for the new node.
+    fn view_auth_key_form<'a>(&'a self, form: &'a AuthKeyForm) -> Element<'a, Message> {
+        let field = |label: String,
+                     placeholder: &'static str,
+                     value: &'a str,
+                     on_input: fn(String) -> Message| {
+            settings::item(
+                label,
+                text_input(placeholder, value).on_input(on_input).width(250),
+            )
+        };
+
+        let elements = list_column()
+            .list_item_padding(5)
+            .add(settings::item(
+                fl!("auth-key-key"),
+                text_input("tskey-auth-…", &form.auth_key)
+                    .password()
+                    .on_input(Message::AuthKeyInput)
+                    .width(250),
+            ))
+            .add(field(
+                fl!("auth-key-tags"),
+                "tag:server, tag:ci",
+                &form.tags,
+                Message::AuthKeyTagsInput,
+            ))
+            .add(field(
+                fl!("status-hostname"),
+                "",
+                &form.hostname,
+                Message::AuthKeyHostnameInput,
+            ))
+            .add(field(
+                fl!("advanced-control-url"),
+                "https://controlplane.tailscale.com",
+                &form.control_url,
+                Message::AuthKeyControlUrlInput,
+            ));
+
+        let busy = self.login.as_ref().is_some_and(|login| login.auth_key);
+        let mut col = column![
+            elements,
+            button::suggested(fl!("auth-key-submit"))
+                .on_press_maybe((!busy).then_some(Message::SubmitAuthKey)),
+        ]
+        .spacing(8);
+        if !form.error.is_empty() {
+            col = col.push(text(&form.error).size(11));
+        }
+        col.into()
+    }
+
+    /// Shields-up toggle with the current network and whether it's trusted.
+    fn view_shields(&self) -> Element<'_, Message> {
+        let mut col = column![
+            toggler(self.state.prefs.shields_up)
+                .on_toggle(|val| Message::SetPref(PrefEdit::ShieldsUp(val)))
+        ]
+        .spacing(4)
+        .align_x(Alignment::End);
+
+        if let Some(network) = &self.current_network {
+            let trusted = self.preferences.trusted_networks.contains(&network.id);
+            let trust_label = if trusted {
+                fl!("shields-untrust")
+            } else {
+                fl!("shields-trust")
+            };
+            col = col.push(
+                row![
+                    text(&network.id).size(11),
+                    button::text(trust_label)
+                        .on_press(Message::ToggleTrustedNetwork(network.id.clone())),
+                ]
+                .spacing(8)
+                .align_y(Alignment::Center),
+            );
+        }
+
+        col.into()
+    }
+
+    fn matching_network_rule(&self) -> Option<&NetworkRule> {
+        matching_rule(
+            &self.preferences.network_rules,
+            self.current_network.as_ref()?,
+            &self.preferences.trusted_networks,
+        )
+    }
+
+    /// Bring connection and exit node in line with the rule matching the
+    /// current network, changing only what differs.
+    fn apply_network_rules(&self) -> Vec<Task<Action<Message>>> {
+        let mut tasks = Vec::new();
+        // A pause outranks the rules until it ends.
+        if self.is_paused() {
+            return tasks;
+        }
+        let Some(rule) = self.matching_network_rule() else {
+            return tasks;
+        };
+        let state = &self.state;
+
+        let (connect, exit_node) = match &rule.action {
+            RuleAction::Disconnect => (false, &ExitNodeRule::Keep),
+            RuleAction::Connect(exit_node) => (true, exit_node),
+        };
+        if connect != state.connected {
+            let client = self.client.clone();
+            tasks.push(task::future(async move {
+                match set_connected(&client, connect).await {
+                    Ok(()) => Message::ActionCompleted(Ok(())),
+                    Err(e) => Message::ActionCompleted(Err(format!("set_connected: {e}"))),
+                }
+            }));
+        }
+
+        let current_exit = state
+            .devices
+            .iter()
+            .find(|dev| dev.is_exit_node && !dev.is_self)
+            .map(|dev| dev.name.as_str());
+        let node_ip = match exit_node {
+            ExitNodeRule::Keep => None,
+            ExitNodeRule::Clear => current_exit.map(|_| String::new()),
+            ExitNodeRule::Use(name) if current_exit != Some(name.as_str()) => state
+                .exit_node_options
+                .iter()
+                .find(|dev| dev.name == *name)
+                .and_then(|dev| dev.tailscale_ips.first().cloned()),
+            ExitNodeRule::Use(_) => None,
+        };
+        if let Some(node_ip) = node_ip {
+            let client = self.client.clone();
+            tasks.push(task::future(async move {
+                match set_exit_node(&client, &node_ip).await {
+                    Ok(()) => Message::ActionCompleted(Ok(())),
+                    Err(e) => Message::ActionCompleted(Err(format!("set_exit_node: {e}"))),
+                }
+            }));
+        }
+
+        tasks
+    }
+
+    /// Take on preferences loaded from elsewhere: re-sync the inputs that
+    /// edit them and re-apply the rules that depend on them.
+    fn replace_preferences(&mut self, prefs: AppPreferences) -> Vec<Task<Action<Message>>> {
+        let mut tasks = Vec::new();
+        let old = std::mem::replace(&mut self.preferences, prefs);
+        let prefs = &self.preferences;
+        if old.poll_interval_secs != prefs.poll_interval_secs {
+            self.poll_interval_input = prefs.poll_interval_secs.to_string();
+        }
+        if old.key_expiry_reminder_days != prefs.key_expiry_reminder_days {
+            self.key_expiry_input = format_reminder_days(&prefs.key_expiry_reminder_days);
+        }
+        if old.terminal != prefs.terminal {
+            self.terminal_input = prefs.terminal.clone();
+        }
+        if old.ssh_user != prefs.ssh_user {
+            self.ssh_user_input = prefs.ssh_user.clone();
+        }
+        if old.presence_debounce_secs != prefs.presence_debounce_secs {
+            self.presence_debounce_input = prefs.presence_debounce_secs.to_string();
+        }
+        if old.schedule_connect != prefs.schedule_connect
+            || old.schedule_disconnect != prefs.schedule_disconnect
+        {
+            self.schedule_connect_input = prefs.schedule_connect.clone();
+            self.schedule_disconnect_input = prefs.schedule_disconnect.clone();
+            self.schedule_error.clear();
+        }
+        if old.notification_styles != prefs.notification_styles {
+            self.notifications
+                .set_styles(prefs.notification_styles.clone());
+        }
+        if old.global_shortcuts != prefs.global_shortcuts {
+            match set_global_shortcuts(prefs.global_shortcuts) {
+                Ok(()) => self.shortcuts_status.clear(),
+                Err(e) => {
+                    self.shortcuts_status = format!("{}: {e}", fl!("shortcuts-failed"));
+                }
+            }
+        }
+        if old.auto_shields_up != self.preferences.auto_shields_up
+            || old.trusted_networks != self.preferences.trusted_networks
+        {
+            tasks.extend(self.apply_shields_rule());
+        }
+        if old.network_rules != self.preferences.network_rules {
+            tasks.extend(self.apply_network_rules());
+        }
+        tasks
+    }
+
+    /// Remember the outgoing profile's exit node, download directory and
+    /// notification choice, and restore the incoming profile's.
+    fn switch_profile_prefs(
+        &mut self,
+        new_state: &TailscaleState,
+    ) -> Option<Task<Action<Message>>> {
+        let current_profile = |state: &TailscaleState| {
+            state
+                .accounts
+                .iter()
+                .find(|acct| acct.is_current)
+                .map(|acct| acct.id.clone())
+        };
+        let from = current_profile(&self.state)?;
+        let to = current_profile(new_state)?;
+
+        let mut profile_prefs = self.preferences.profile_prefs.clone();
+        profile_prefs.insert(
+            from,
+            ProfilePrefs {
+                exit_node: current_exit_node(&self.state),
+                download_dir: self.preferences.download_dir.clone(),
+                notifications_enabled: self.preferences.notifications_enabled,
+            },
+        );
+        // A profile seen for the first time keeps the current preferences
+        // and the daemon's exit node.
+        let incoming = profile_prefs
+            .get(&to)
+            .cloned()
+            .unwrap_or_else(|| ProfilePrefs {
+                exit_node: current_exit_node(new_state),
+                download_dir: self.preferences.download_dir.clone(),
+                notifications_enabled: self.preferences.notifications_enabled,
+            });
+        self.save_pref(|prefs, config| prefs.set_profile_prefs(config, profile_prefs));
+        self.save_pref(|prefs, config| prefs.set_download_dir(config, incoming.download_dir));
+        self.save_pref(|prefs, config| {
+            prefs.set_notifications_enabled(config, incoming.notifications_enabled)
+        });
+
+        // An exit node the new profile doesn't offer is left to the daemon.
+        let position = |name: &String| {
+            new_state
+                .exit_node_options
+                .iter()
+                .position(|dev| dev.name == *name)
+        };
+        let exit_node = match incoming.exit_node {
+            Some(name) if position(&name).is_none() => current_exit_node(new_state),
+            exit_node => exit_node,
+        };
+        let exit_idx = exit_node.as_ref().and_then(position);
+        let sel_idx = exit_idx.map_or(0, |idx| idx + 1);
+        self.sel_exit_node_idx = Some(sel_idx);
+        self.save_pref(|prefs, config| prefs.set_exit_node_idx(config, Some(sel_idx)));
+        self.save_pref(|prefs, config| prefs.set_exit_node(config, exit_node.clone()));
+
+        // Enforcing preferences sets the exit node once connected.
+        if self.preferences.enforce_prefs
+            || new_state.is_exit_node
+            || current_exit_node(new_state) == exit_node
+        {
+            return None;
+        }
+
+        let node_ip = exit_idx
+            .and_then(|idx| {
+                new_state.exit_node_options[idx]
+                    .tailscale_ips
+                    .first()
+                    .cloned()
+            })
+            .unwrap_or_default();
+        let client = self.client.clone();
+        Some(task::future(async move {
+            match set_exit_node(&client, &node_ip).await {
+                Ok(()) => Message::ActionCompleted(Ok(())),
+                Err(e) => Message::ActionCompleted(Err(format!("set_exit_node: {e}"))),
+            }
+        }))
+    }
+
+    /// Re-apply the user's SSH, route, MagicDNS, LAN access and exit node
+    /// choices where the daemon drifted from them, and report what was
+    /// corrected.
+    fn enforce_preferences(&mut self) -> Vec<Task<Action<Message>>> {
+        let mut tasks = Vec::new();
+        let prefs = &self.preferences;
+        let state = &self.state;
+
+        // A network rule that picks the exit node outranks the stored
+        // choice, and a node advertising itself as exit node can't use one.
+        let rule_sets_exit_node = self.matching_network_rule().is_some_and(|rule| {
+            matches!(
+                rule.action,
+                RuleAction::Connect(ExitNodeRule::Clear | ExitNodeRule::Use(_))
+            )
+        });
+        let exit_node = if rule_sets_exit_node || state.is_exit_node {
+            ExitNodeRule::Keep
+        } else {
+            match &prefs.exit_node {
+                Some(name) => ExitNodeRule::Use(name.clone()),
+                None => ExitNodeRule::Clear,
+            }
+        };
+        let wanted = EnforcedPrefs {
+            ssh: prefs.ssh_enabled,
+            accept_routes: prefs.routes_accepted,
+            magic_dns: prefs.magic_dns,
+            exit_node_allow_lan: prefs.allow_lan,
+            exit_node,
+        };
+
+        let on_off = |on: bool| {
+            if on {
+                fl!("enforce-on")
+            } else {
+                fl!("enforce-off")
+            }
+        };
+        let mut corrected = Vec::new();
+        for correction in drift(state, &wanted) {
+            let client = self.client.clone();
+            match correction {
+                Correction::Ssh(enabled) => {
+                    corrected.push(format!("{}: {}", fl!("status-enable-ssh"), on_off(enabled)));
+                    tasks.push(task::future(async move {
+                        match set_ssh(&client, enabled).await {
+                            Ok(()) => Message::ActionCompleted(Ok(())),
+                            Err(e) => Message::ActionCompleted(Err(format!("set_ssh: {e}"))),
+                        }
+                    }));
+                }
+                Correction::AcceptRoutes(accepted) => {
+                    corrected.push(format!(
+                        "{}: {}",
+                        fl!("status-accept-routes"),
+                        on_off(accepted)
+                    ));
+                    tasks.push(task::future(async move {
+                        match set_routes(&client, accepted).await {
+                            Ok(()) => Message::ActionCompleted(Ok(())),
+                            Err(e) => Message::ActionCompleted(Err(format!("set_routes: {e}"))),
+                        }
+                    }));
+                }
+                Correction::MagicDns(enabled) => {
+                    corrected.push(format!("{}: {}", fl!("status-magic-dns"), on_off(enabled)));
+                    tasks.push(task::future(async move {
+                        match set_magic_dns(&client, enabled).await {
+                            Ok(()) => Message::ActionCompleted(Ok(())),
+                            Err(e) => Message::ActionCompleted(Err(format!("set_magic_dns: {e}"))),
+                        }
+                    }));
+                }
+                Correction::ExitNodeAllowLan(allow) => {
+                    corrected.push(format!(
+                        "{}: {}",
+                        fl!("status-allow-lan-access"),
+                        on_off(allow)
+                    ));
+                    tasks.push(task::future(async move {
+                        match set_exit_node_allow_lan(&client, allow).await {
+                            Ok(()) => Message::ActionCompleted(Ok(())),
+                            Err(e) => Message::ActionCompleted(Err(format!(
+                                "set_exit_node_allow_lan: {e}"
+                            ))),
+                        }
+                    }));
+                }
+                Correction::ExitNode(to) => {
+                    // The stored exit node may not be offered right now.
+                    let node_ip = match &to {
+                        Some(name) => match state
+                            .exit_node_options
+                            .iter()
+                            .find(|dev| dev.name == *name)
+                            .and_then(|dev| dev.tailscale_ips.first())
+                        {
+                            Some(ip) => ip.clone(),
+                            None => continue,
+                        },
+                        None => String::new(),
+                    };
+                    corrected.push(format!(
+                        "{}: {}",
+                        fl!("flash-exit-node"),
+                        to.unwrap_or_else(|| fl!("none-default"))
+                    ));
+                    tasks.push(task::future(async move {
+                        match set_exit_node(&client, &node_ip).await {
+                            Ok(()) => Message::ActionCompleted(Ok(())),
+                            Err(e) => Message::ActionCompleted(Err(format!("set_exit_node: {e}"))),
+                        }
+                    }));
+                }
+            }
+        }
+
+        if !corrected.is_empty() {
+            let corrected = corrected.join(", ");
+            if self.preferences.notifications_enabled {
+                self.notifications.prefs_restored(&corrected);
+            }
+            tasks.push(self.show_flash(format!("{}: {corrected}", fl!("flash-prefs-restored"))));
+        }
+        tasks
+    }
+
+    /// Show `flash` in the open popup until it is replaced or times out.
+    fn show_flash(&mut self, flash: String) -> Task<Action<Message>> {
+        self.flash_seq += 1;
+        self.flash = Some((self.flash_seq, flash));
+        let seq = self.flash_seq;
+        task::future(async move {
+            clear_status(STATUS_CLEAR_TIME).await;
+            Message::ClearFlash(seq)
+        })
+    }
+
+    /// Change the icon or sound of one kind of notification. Kinds back to
+    /// the defaults are dropped from the config.
+    fn edit_notification_style<F>(&mut self, kind: NotificationKind, edit: F)
+    where
+        F: FnOnce(&mut NotificationStyle),
+    {
+        let mut styles = self.preferences.notification_styles.clone();
+        let style = styles.entry(kind).or_default();
+        edit(style);
+        if *style == NotificationStyle::default() {
+            styles.remove(&kind);
+        }
+        self.notifications.set_styles(styles.clone());
+        self.save_pref(|prefs, config| prefs.set_notification_styles(config, styles));
+    }
+
+    /// Save the settings text inputs once typing pauses, rather than
+    /// writing the config on every keystroke.
+    fn schedule_input_save(&mut self) -> Task<Action<Message>> {
+        self.input_save_seq += 1;
+        let seq = self.input_save_seq;
+        task::future(async move {
+            tokio::time::sleep(INPUT_SAVE_DELAY).await;
+            Message::SaveInputs(seq)
+        })
+    }
+
+    fn save_inputs(&mut self) {
+        let terminal = self.terminal_input.clone();
+        let ssh_user = self.ssh_user_input.clone();
+        self.save_pref(|prefs, config| prefs.set_terminal(config, terminal));
+        self.save_pref(|prefs, config| prefs.set_ssh_user(config, ssh_user));
+        if let Some(days) = parse_reminder_days(&self.key_expiry_input) {
+            self.save_pref(|prefs, config| prefs.set_key_expiry_reminder_days(config, days));
+        }
+        if let Ok(secs) = self.presence_debounce_input.trim().parse::<u64>()
+            && secs <= MAX_PRESENCE_DEBOUNCE_SECS
+        {
+            self.save_pref(|prefs, config| prefs.set_presence_debounce_secs(config, secs));
+        }
+    }
+
+    fn is_paused(&self) -> bool {
+        self.preferences
+            .paused_until
+            .is_some_and(|until| until > Utc::now().timestamp())
+    }
+
+    fn set_paused_until(&mut self, until: Option<i64>) {
+        self.save_pref(|prefs, config| prefs.set_paused_until(config, until));
+    }
+
+    /// Persist a preference through its typed setter, which also updates
+    /// `self.preferences`.
+    fn save_pref<F>(&mut self, set: F)
+    where
+        F: FnOnce(&mut AppPreferences, &Config) -> Result<bool, cosmic_config::Error>,
+    {
+        let old = self.preferences.clone();
+        if let Err(e) = set(&mut self.preferences, &self.config) {
+            eprintln!("Failed to save preference: {e}");
+        }
+        self.note_own_writes(changed_keys(&old, &self.preferences));
+    }
+
+    /// Remember writes to `keys` so their config echoes are ignored.
+    fn note_own_writes(&mut self, keys: Vec<String>) {
+        let now = Instant::now();
+        for key in keys {
+            self.own_writes.insert(key, now);
+        }
+    }
+
+    /// Persist the schedule inputs that are valid ("HH:MM" or empty).
+    fn save_schedules(&mut self) {
+        let valid = |time: &str| time.trim().is_empty() || parse_schedule_time(time).is_some();
+        if !valid(&self.schedule_connect_input) || !valid(&self.schedule_disconnect_input) {
+            self.schedule_error = fl!("schedule-invalid");
+            return;
+        }
+        self.schedule_error.clear();
+
+        let connect = self.schedule_connect_input.trim().to_string();
+        let disconnect = self.schedule_disconnect_input.trim().to_string();
+        self.save_pref(|prefs, config| prefs.set_schedule_connect(config, connect));
+        self.save_pref(|prefs, config| prefs.set_schedule_disconnect(config, disconnect));
+    }
+
+    /// Pause buttons while connected, or the pause end and a resume button
+    /// while paused.
+    fn view_pause(&self) -> Element<'_, Message> {
+        if let Some(until) = self.preferences.paused_until.filter(|_| self.is_paused()) {
+            let until = Local
+                .timestamp_opt(until, 0)
+                .single()
+                .map(|until| until.format("%a %H:%M").to_string())
+                .unwrap_or_default();
+            return row![
+                text(format!("{} {until}", fl!("pause-until"))).size(11),
+                button::standard(fl!("pause-resume")).on_press(Message::Resume),
+            ]
+            .spacing(8)
+            .align_y(Alignment::Center)
+            .into();
+        }
+
+        if !self.state.connected {
+            return row![].into();
+        }
+
+        PauseDuration::ALL
+            .iter()
+            .fold(
+                row![text(fl!("pause-title")).size(11)]
+                    .spacing(4)
+                    .align_y(Alignment::Center),
+                |row, duration| {
+                    let label = match duration {
+                        PauseDuration::Minutes15 => fl!("pause-15-min"),
+                        PauseDuration::Hour => fl!("pause-1-hour"),
+                        PauseDuration::UntilTomorrow => fl!("pause-tomorrow"),
+                    };
+                    row.push(button::text(label).on_press(Message::Pause(*duration)))
+                },
+            )
+            .into()
+    }
+
+    /// With the auto rule on, shields go up on untrusted networks and down
+    /// on trusted ones. Nothing changes while offline.
+    fn apply_shields_rule(&self) -> Option<Task<Action<Message>>> {
+        let network = self.current_network.as_ref()?;
+        if !self.preferences.auto_shields_up {
+            return None;
+        }
+
+        let shields_up = !self.preferences.trusted_networks.contains(&network.id);
+        if shields_up == self.state.prefs.shields_up {
+            return None;
+        }
+        let edit = PrefEdit::ShieldsUp(shields_up);
+        Some(task::future(async move { Message::SetPref(edit) }))
+    }
+
+    /// Editable hostname with the current MagicDNS name, or a live preview
+    /// of the name an edit would produce.
+    fn view_hostname_editor(&self) -> Element<'_, Message> {
+        let state = &self.state;
+        let value = self.hostname_draft.as_deref().unwrap_or(&state.hostname);
+        let editing = self
+            .hostname_draft
+            .as_ref()
+            .is_some_and(|draft| draft.trim() != state.hostname);
+
+        let mut save = button::standard(fl!("hostname-save"));
+        if editing {
+            save = save.on_press(Message::SaveHostname);
+        }
+
+        let detail = if !self.hostname_status.is_empty() {
+            self.hostname_status.clone()
+        } else if editing {
+            let draft = value.trim();
+            if draft.is_empty() {
+                fl!("hostname-default")
+            } else if valid_dns_label(draft) {
+                format!("→ {}", preview_fqdn(draft, &state.dns_suffix))
+            } else {
+                fl!("hostname-invalid")
+            }
+        } else {
+            state.dns_name.clone()
+        };
+
+        column![
+            row![
+                text_input(state.hostname.clone(), value.to_string())
+                    .on_input(Message::HostnameInput)
+                    .width(180),
+                save,
+            ]
+            .spacing(8)
+            .align_y(Alignment::Center),
+            text(detail).size(11),
+        ]
+        .spacing(4)
+        .align_x(Alignment::End)
+        .into()
+    }
+
+    /// Overlap warnings for a subnet route, one line each.
+    fn view_route_overlaps(&self, route: &Cidr) -> Element<'_, Message> {
+        route_overlaps(route, &self.local_networks, &self.state.devices)
+            .into_iter()
+            .fold(column![].spacing(2), |col, overlap| {
+                col.push(text(format!("⚠ {overlap}")).size(11))
+            })
+            .into()
+    }
+
+    /// Favorite devices pinned above the status list, with quick actions.
+    fn view_favorites(&self) -> Element<'_, Message> {
+        let favorites: Vec<_> = self
+            .preferences
+            .favorites
+            .iter()
+            .filter_map(|id| self.state.devices.iter().find(|dev| dev.id == *id))
+            .collect();
+
+        if favorites.is_empty() {
+            return column![].into();
+        }
+
+        let mut section = column![text(fl!("favorites-title")).size(14)]
+            .spacing(4)
+            .padding(4);
+
+        for dev in favorites {
+            let dot = if dev.online { "● " } else { "○ " };
+            let mut fav_row = row![text(format!("{dot}{}", dev.name)).width(Length::Fill)]
+                .spacing(4)
+                .align_y(Alignment::Center);
+
+            if let Some(ip) = dev.tailscale_ips.first() {
+                fav_row = fav_row.push(
+                    button::icon(icon::from_name("edit-copy-symbolic"))
+                        .on_press(Message::CopyToClipboard(ip.clone()))
+                        .tooltip(fl!("devices-copy-ip")),
+                );
+            }
+            if !dev.is_self {
+                if !dev.dns_name.is_empty() {
+                    fav_row = fav_row.push(
+                        button::icon(icon::from_name("utilities-terminal-symbolic"))
+                            .on_press(Message::OpenSsh(dev.dns_name.clone(), dev.run_ssh))
+                            .tooltip(fl!("devices-open-ssh")),
+                    );
+                }
+                if let Some(ip) = dev.tailscale_ips.first() {
+                    fav_row = fav_row.push(
+                        button::icon(icon::from_name("network-transmit-receive-symbolic"))
+                            .on_press(Message::PingDevice(ip.clone()))
+                            .tooltip(fl!("devices-ping")),
+                    );
+                }
+                fav_row = fav_row.push(
+                    button::icon(icon::from_name("send-to-symbolic"))
+                        .on_press(Message::QuickSend(dev.id.clone()))
+                        .tooltip(fl!("favorites-send")),
+                );
+            }
+            section = section.push(fav_row);
+
+            // Show the latest ping result next to the favorite it belongs to.
+            if let Some(ref pr) = self.ping_result
+                && dev.tailscale_ips.contains(&pr.ip)
+            {
+                let line = if !pr.err.is_empty() {
+                    format!("{}: {}", fl!("devices-ping-error"), pr.err)
+                } else {
+                    format!(
+                        "{}: {:.1} ms",
+                        fl!("devices-ping-result"),
+                        pr.latency_seconds * 1000.0
+                    )
+                };
+                section = section.push(text(line).size(11));
+            }
+        }
+
+        section.into()
+    }
+
+    fn view_taildrop_tab(&self) -> Element<'_, Message> {
+        // --- Send section ---
+        let send_header = text(fl!("taildrop-send-title")).size(14);
+
+        let device_picker = row![
+            text(fl!("taildrop-send-to")).width(Length::Shrink),
+            dropdown(
+                &self.state.device_names,
+                self.selected_device_idx,
+                Message::DeviceSelected,
+            ),
+        ]
+        .spacing(8)
+        .align_y(Alignment::Center);
+
+        let mut send_section = column![send_header, device_picker].spacing(6).padding(4);
+
+        if !self.send_files.is_empty() {
+            let mut files_col = column![text(fl!("taildrop-files-queued")).size(12)].spacing(2);
+            for path in &self.send_files {
+                files_col = files_col.push(text(path.clone()).size(11));
+            }
+            send_section = send_section.push(files_col);
+        }
+
+        let send_buttons = row![
+            button::standard(fl!("taildrop-choose-files")).on_press(Message::ChooseFiles),
+            button::suggested(fl!("taildrop-send")).on_press(Message::SendFiles),
+        ]
+        .spacing(8);
+        send_section = send_section.push(send_buttons);
+
+        if !self.send_file_status.is_empty() {
+            send_section = send_section.push(text(self.send_file_status.clone()).size(11));
         }
 
         // --- Receive section ---
@@ -991,117 +3499,430 @@ impl Window {
             recv_section = recv_section.push(text(self.receive_file_status.clone()).size(11));
         }
 
-        column![send_section, recv_section]
-            .spacing(10)
-            .padding(4)
-            .into()
+        column![send_section, recv_section]
+            .spacing(10)
+            .padding(4)
+            .into()
+    }
+
+    fn view_devices_tab(&self) -> Element<'_, Message> {
+        let header = text(fl!("devices-title")).size(14);
+        let mut col = column![header].spacing(4).padding(4);
+
+        if self.state.devices.is_empty() {
+            col = col.push(text(fl!("devices-none")).size(12));
+            return col.into();
+        }
+
+        col = col.push(
+            text_input(fl!("devices-search"), &self.device_query)
+                .on_input(Message::DeviceSearch)
+                .width(Length::Fill),
+        );
+
+        let mut filter_row = row![].spacing(4);
+        for filter in DeviceFilter::ALL {
+            let label = match filter {
+                DeviceFilter::Online => fl!("devices-filter-online"),
+                DeviceFilter::ExitNode => fl!("devices-filter-exit-nodes"),
+                DeviceFilter::Tagged => fl!("devices-filter-tagged"),
+                DeviceFilter::Mullvad => fl!("devices-filter-mullvad"),
+                DeviceFilter::SharedIn => fl!("devices-filter-shared-in"),
+            };
+            let btn = if self.device_filters.contains(&filter) {
+                button::suggested(label)
+            } else {
+                button::standard(label)
+            };
+            filter_row = filter_row.push(btn.on_press(Message::ToggleDeviceFilter(filter)));
+        }
+        col = col.push(filter_row);
+
+        let grouping_idx = DeviceGrouping::ALL
+            .iter()
+            .position(|grouping| *grouping == self.device_grouping);
+        let sort_idx = DeviceSort::ALL
+            .iter()
+            .position(|sort| *sort == self.device_sort);
+        col = col.push(
+            row![
+                text(fl!("devices-group-by")),
+                dropdown(
+                    &self.device_grouping_names,
+                    grouping_idx,
+                    Message::DeviceGroupingSelected,
+                ),
+                text(fl!("devices-sort-by")),
+                dropdown(
+                    &self.device_sort_names,
+                    sort_idx,
+                    Message::DeviceSortSelected
+                ),
+            ]
+            .spacing(8)
+            .align_y(Alignment::Center),
+        );
+
+        let groups = device_view(
+            &self.state.devices,
+            &self.device_query,
+            &self.device_filters,
+            self.device_grouping,
+            self.device_sort,
+            &self.device_latencies,
+        );
+
+        if groups.is_empty() {
+            col = col.push(text(fl!("devices-no-match")).size(12));
+            return col.into();
+        }
+
+        for (key, members) in groups {
+            if self.device_grouping != DeviceGrouping::None {
+                let title = if key.is_empty() {
+                    match self.device_grouping {
+                        DeviceGrouping::Tag => fl!("devices-group-untagged"),
+                        _ => fl!("devices-group-unknown"),
+                    }
+                } else {
+                    key
+                };
+                col = col.push(text(format!("{title} ({})", members.len())).size(13));
+            }
+            for idx in members {
+                col = col.push(self.view_device_entry(idx));
+            }
+        }
+
+        col.into()
     }
 
-    fn view_devices_tab(&self) -> Element<'_, Message> {
-        let header = text(fl!("devices-title")).size(14);
-        let mut col = column![header].spacing(4).padding(4);
-
-        if self.state.devices.is_empty() {
-            col = col.push(text(fl!("devices-none")).size(12));
-            return col.into();
-        }
+    fn view_device_entry(&self, idx: usize) -> Element<'_, Message> {
+        let dev = &self.state.devices[idx];
+        let mut col = column![];
 
-        for (idx, dev) in self.state.devices.iter().enumerate() {
-            let expanded = self.selected_device_detail_idx == Some(idx);
-            let dot = if dev.online { "● " } else { "○ " };
-            let label = if dev.is_self {
-                format!("{dot}{} ({})", dev.name, fl!("devices-self"))
-            } else {
-                format!("{dot}{}", dev.name)
-            };
+        let expanded = self.selected_device_detail_idx == Some(idx);
+        let dot = if dev.online { "● " } else { "○ " };
+        let label = if dev.is_self {
+            format!("{dot}{} ({})", dev.name, fl!("devices-self"))
+        } else {
+            format!("{dot}{}", dev.name)
+        };
 
-            let header_btn = button::text(label)
-                .on_press(Message::SelectDeviceDetail(idx))
-                .width(Length::Fill);
-            col = col.push(header_btn);
+        let header_btn = button::text(label)
+            .on_press(Message::SelectDeviceDetail(idx))
+            .width(Length::Fill);
+        col = col.push(header_btn);
 
-            if expanded {
-                let mut detail = column![].spacing(3).padding([0, 0, 4, 16]);
+        if expanded {
+            let mut detail = column![].spacing(3).padding([0, 0, 4, 16]);
 
-                if let Some(ipv4) = dev.tailscale_ips.first() {
-                    detail = detail.push(detail_row(fl!("status-ipv4"), ipv4.clone()));
-                }
-                if let Some(ipv6) = dev.tailscale_ips.get(1) {
-                    detail = detail.push(detail_row(fl!("status-ipv6"), ipv6.clone()));
-                }
-                if !dev.os.is_empty() {
-                    detail = detail.push(detail_row(fl!("devices-os"), dev.os.clone()));
-                }
-                if !dev.dns_name.is_empty() {
-                    detail = detail.push(detail_row(fl!("devices-dns"), dev.dns_name.clone()));
-                }
-                if !dev.relay.is_empty() {
-                    detail = detail.push(detail_row(fl!("devices-relay"), dev.relay.clone()));
-                }
-                if !dev.tags.is_empty() {
-                    detail = detail.push(detail_row(fl!("devices-tags"), dev.tags.join(", ")));
-                }
-                detail = detail.push(detail_row(fl!("devices-rx"), format_bytes(dev.rx_bytes)));
-                detail = detail.push(detail_row(fl!("devices-tx"), format_bytes(dev.tx_bytes)));
-                if !dev.online && !dev.last_seen.is_empty() {
-                    detail =
-                        detail.push(detail_row(fl!("devices-last-seen"), dev.last_seen.clone()));
+            if let Some(ipv4) = dev.tailscale_ips.first() {
+                detail = detail.push(detail_row(fl!("status-ipv4"), ipv4.clone()));
+            }
+            if let Some(ipv6) = dev.tailscale_ips.get(1) {
+                detail = detail.push(detail_row(fl!("status-ipv6"), ipv6.clone()));
+            }
+            if !dev.os.is_empty() {
+                detail = detail.push(detail_row(fl!("devices-os"), dev.os.clone()));
+            }
+            if !dev.dns_name.is_empty() {
+                detail = detail.push(detail_row(fl!("devices-dns"), dev.dns_name.clone()));
+            }
+            if !dev.relay.is_empty() {
+                detail = detail.push(detail_row(fl!("devices-relay"), dev.relay.clone()));
+            }
+            if !dev.tags.is_empty() {
+                detail = detail.push(detail_row(fl!("devices-tags"), dev.tags.join(", ")));
+            }
+            if !dev.is_self {
+                for (i, peer_route) in
+                    peer_routes(dev, self.state.accept_routes, &self.local_networks)
+                        .iter()
+                        .enumerate()
+                {
+                    let label = if i == 0 {
+                        fl!("devices-subnet-routes")
+                    } else {
+                        String::new()
+                    };
+                    detail = detail.push(detail_row(label, peer_route_label(peer_route)));
                 }
+            }
+            detail = detail.push(detail_row(fl!("devices-rx"), format_bytes(dev.rx_bytes)));
+            detail = detail.push(detail_row(fl!("devices-tx"), format_bytes(dev.tx_bytes)));
+            if !dev.online && !dev.last_seen.is_empty() {
+                detail = detail.push(detail_row(fl!("devices-last-seen"), dev.last_seen.clone()));
+            }
+            if dev.key_expired {
+                detail = detail.push(detail_row(
+                    fl!("devices-key-expiry"),
+                    fl!("devices-key-expired"),
+                ));
+            } else if let Some(expiry) = dev.key_expiry {
+                detail = detail.push(detail_row(
+                    fl!("devices-key-expiry"),
+                    format_key_expiry(expiry, Utc::now()),
+                ));
+            }
 
-                let mut actions = row![].spacing(8);
-                if !dev.is_self {
-                    if let Some(ip) = dev.tailscale_ips.first() {
-                        actions = actions.push(
-                            button::standard(fl!("devices-ping"))
-                                .on_press(Message::PingDevice(ip.clone())),
-                        );
-                    }
-                }
+            let mut actions = row![].spacing(8);
+            if !dev.is_self {
                 if let Some(ip) = dev.tailscale_ips.first() {
                     actions = actions.push(
-                        button::standard(fl!("devices-copy-ip"))
-                            .on_press(Message::CopyToClipboard(ip.clone())),
+                        button::standard(fl!("devices-ping"))
+                            .on_press(Message::PingDevice(ip.clone())),
                     );
                 }
-                if dev.exit_node_option && !dev.is_self {
-                    let label = if dev.is_exit_node {
-                        fl!("devices-exit-current")
+            }
+            if let Some(ip) = dev.tailscale_ips.first() {
+                actions = actions.push(
+                    button::standard(fl!("devices-copy-ip"))
+                        .on_press(Message::CopyToClipboard(ip.clone())),
+                );
+            }
+            if !dev.is_self {
+                let is_watched = self.preferences.watched_devices.contains(&dev.id);
+                actions = actions.push(
+                    button::standard(if is_watched {
+                        fl!("devices-unwatch")
                     } else {
-                        fl!("devices-use-as-exit")
-                    };
-                    let target_idx = self
-                        .exit_node_names
-                        .iter()
-                        .position(|n| n == &dev.name)
-                        .unwrap_or(0);
-                    actions = actions.push(
-                        button::standard(label).on_press(Message::ExitNodeSelected(target_idx)),
-                    );
+                        fl!("devices-watch")
+                    })
+                    .on_press(Message::ToggleWatch(dev.id.clone())),
+                );
+            }
+            let is_favorite = self.preferences.favorites.contains(&dev.id);
+            actions = actions.push(
+                button::standard(if is_favorite {
+                    fl!("favorites-remove")
+                } else {
+                    fl!("favorites-add")
+                })
+                .on_press(Message::ToggleFavorite(dev.id.clone())),
+            );
+            if dev.is_self && (dev.key_expired || dev.key_expiry.is_some()) {
+                actions = actions.push(
+                    button::standard(fl!("devices-reauthenticate"))
+                        .on_press(Message::Reauthenticate),
+                );
+            }
+            if dev.exit_node_option && !dev.is_self {
+                let label = if dev.is_exit_node {
+                    fl!("devices-exit-current")
+                } else {
+                    fl!("devices-use-as-exit")
+                };
+                let target_idx = self
+                    .exit_node_names
+                    .iter()
+                    .position(|n| n == &dev.name)
+                    .unwrap_or(0);
+                actions = actions
+                    .push(button::standard(label).on_press(Message::ExitNodeSelected(target_idx)));
+            }
+            detail = detail.push(actions);
+
+            if !dev.dns_name.is_empty() {
+                let host = dev.dns_name.clone();
+                let mut open_actions = row![
+                    button::standard(fl!("devices-copy-dns"))
+                        .on_press(Message::CopyToClipboard(host.clone())),
+                ]
+                .spacing(8);
+
+                if !dev.is_self {
+                    let sftp_url = sftp_url(&self.preferences.ssh_user, &host);
+                    open_actions = open_actions
+                        .push(
+                            button::standard(fl!("devices-open-ssh"))
+                                .on_press(Message::OpenSsh(host.clone(), dev.run_ssh)),
+                        )
+                        .push(
+                            button::standard(fl!("devices-open-http"))
+                                .on_press(Message::OpenUrl(format!("http://{host}"))),
+                        )
+                        .push(
+                            button::standard(fl!("devices-open-https"))
+                                .on_press(Message::OpenUrl(format!("https://{host}"))),
+                        )
+                        .push(
+                            button::standard(fl!("devices-open-files"))
+                                .on_press_maybe(sftp_url.map(Message::OpenUrl)),
+                        );
                 }
-                detail = detail.push(actions);
+                detail = detail.push(open_actions);
+            }
 
-                if self.ping_in_progress {
-                    detail = detail.push(text(fl!("devices-pinging")).size(11));
-                } else if let Some(ref pr) = self.ping_result {
-                    let line = if !pr.err.is_empty() {
-                        format!("{}: {}", fl!("devices-ping-error"), pr.err)
-                    } else {
-                        format!(
-                            "{}: {:.1} ms ({})",
-                            fl!("devices-ping-result"),
-                            pr.latency_seconds * 1000.0,
-                            if pr.is_direct {
-                                fl!("devices-direct")
-                            } else {
-                                fl!("devices-relayed")
-                            }
+            if self.ping_in_progress {
+                detail = detail.push(text(fl!("devices-pinging")).size(11));
+            } else if let Some(ref pr) = self.ping_result {
+                let line = if !pr.err.is_empty() {
+                    format!("{}: {}", fl!("devices-ping-error"), pr.err)
+                } else {
+                    format!(
+                        "{}: {:.1} ms ({})",
+                        fl!("devices-ping-result"),
+                        pr.latency_seconds * 1000.0,
+                        if pr.is_direct {
+                            fl!("devices-direct")
+                        } else {
+                            fl!("devices-relayed")
+                        }
+                    )
+                };
+                detail = detail.push(text(line).size(11));
+            }
+
+            col = col.push(detail);
+        }
+
+        col.into()
+    }
+
+    /// Subnet routes advertised by peers and whether this node uses them.
+    fn view_routes_tab(&self) -> Element<'_, Message> {
+        let mut col = column![
+            text(fl!("routes-title")).size(14),
+            settings::item(
+                fl!("status-accept-routes"),
+                container(toggler(self.state.accept_routes).on_toggle(Message::AcceptRoutes))
+                    .align_x(Alignment::End),
+            ),
+        ]
+        .spacing(4)
+        .padding(4);
+
+        let mut any_routes = false;
+        for dev in self.state.devices.iter().filter(|dev| !dev.is_self) {
+            let routes = peer_routes(dev, self.state.accept_routes, &self.local_networks);
+            if routes.is_empty() {
+                continue;
+            }
+            any_routes = true;
+
+            col = col.push(text(&dev.name).size(13));
+            for peer_route in &routes {
+                col = col.push(
+                    row![
+                        text(peer_route.route.to_string())
+                            .size(12)
+                            .width(Length::Fill),
+                        text(peer_route_status(peer_route.status)).size(11),
+                    ]
+                    .spacing(8)
+                    .padding([0, 0, 0, 16]),
+                );
+                for (iface, network) in &peer_route.lan_conflicts {
+                    col = col.push(
+                        container(
+                            text(format!(
+                                "⚠ {}: {network} ({iface})",
+                                fl!("routes-lan-conflict")
+                            ))
+                            .size(11),
                         )
-                    };
-                    detail = detail.push(text(line).size(11));
+                        .padding([0, 0, 0, 16]),
+                    );
                 }
+            }
+        }
+
+        if !any_routes {
+            col = col.push(text(fl!("routes-none")).size(12));
+        }
+
+        col.into()
+    }
+
+    fn view_tailnet_lock_tab(&self) -> Element<'_, Message> {
+        let header = text(fl!("tka-title")).size(14);
+        let mut col = column![header].spacing(4).padding(4);
+
+        let Some(ref lock) = self.state.tailnet_lock else {
+            col = col.push(text(fl!("tka-unavailable")).size(12));
+            return col.into();
+        };
+
+        if !lock.enabled {
+            col = col.push(text(fl!("tka-disabled")).size(12));
+            return col.into();
+        }
+
+        col = col.push(detail_row(fl!("tka-status"), fl!("tka-enabled")));
+        col = col.push(detail_row(
+            fl!("tka-this-node"),
+            if lock.node_key_signed {
+                fl!("tka-node-signed")
+            } else {
+                fl!("tka-node-unsigned")
+            },
+        ));
+        if !lock.public_key.is_empty() {
+            col = col.push(
+                row![
+                    text(fl!("tka-signing-key"))
+                        .size(11)
+                        .width(Length::Fixed(80.0)),
+                    text(lock.public_key.clone()).size(11).width(Length::Fill),
+                    button::icon(icon::from_name("edit-copy-symbolic"))
+                        .on_press(Message::CopyToClipboard(lock.public_key.clone()))
+                        .tooltip(fl!("copy-tooltip")),
+                ]
+                .spacing(8)
+                .align_y(Alignment::Center),
+            );
+        }
+        col = col.push(detail_row(
+            fl!("tka-signing-node"),
+            if lock.is_signing_node {
+                fl!("tka-signing-allowed")
+            } else {
+                fl!("tka-signing-not-allowed")
+            },
+        ));
+
+        // Trusted keys
+        col = col.push(text(fl!("tka-trusted-keys")).size(14));
+        for key in &lock.trusted_keys {
+            let line = if key.is_self {
+                format!("{} ({})", key.key, fl!("devices-self"))
+            } else {
+                key.key.clone()
+            };
+            col = col.push(
+                row![
+                    text(line).size(11).width(Length::Fill),
+                    text(format!("{}: {}", fl!("tka-votes"), key.votes)).size(11),
+                ]
+                .spacing(8),
+            );
+        }
+
+        // Peers locked out until signed
+        col = col.push(text(fl!("tka-awaiting-signature")).size(14));
+        if lock.locked_out.is_empty() {
+            col = col.push(text(fl!("tka-none-awaiting")).size(12));
+        }
+        for peer in &lock.locked_out {
+            let mut peer_row = row![
+                text(peer.name.clone()).width(Length::Fill),
+                text(peer.tailscale_ips.first().cloned().unwrap_or_default()).size(11),
+            ]
+            .spacing(8)
+            .align_y(Alignment::Center);
 
-                col = col.push(detail);
+            if lock.is_signing_node {
+                peer_row = peer_row.push(
+                    button::suggested(fl!("tka-sign"))
+                        .on_press(Message::SignNode(peer.node_key.clone())),
+                );
             }
+            col = col.push(peer_row);
+        }
+
+        if !self.tka_status.is_empty() {
+            col = col.push(text(self.tka_status.clone()).size(11));
         }
 
         col.into()
@@ -1120,6 +3941,10 @@ impl Window {
                 fl!("settings-auto-connect"),
                 toggler(prefs.auto_connect).on_toggle(Message::SetAutoConnect),
             ))
+            .add(settings::item(
+                fl!("settings-enforce-prefs"),
+                toggler(prefs.enforce_prefs).on_toggle(Message::SetEnforcePrefs),
+            ))
             .add(settings::item(
                 fl!("settings-icon-dynamic"),
                 toggler(prefs.icon_style == "dynamic").on_toggle(Message::SetIconStyle),
@@ -1136,10 +3961,53 @@ impl Window {
                 fl!("settings-notify-files"),
                 toggler(prefs.notify_on_incoming_files).on_toggle(Message::SetNotifyFiles),
             ))
+            .add(settings::item(
+                fl!("settings-auto-shields-up"),
+                toggler(prefs.auto_shields_up).on_toggle(Message::SetAutoShieldsUp),
+            ))
             .add(settings::item(
                 fl!("settings-notify-device"),
                 toggler(prefs.notify_on_new_device).on_toggle(Message::SetNotifyDevice),
             ))
+            .add(settings::item(
+                fl!("settings-notify-favorites"),
+                toggler(prefs.notify_on_favorite_presence)
+                    .on_toggle(Message::SetNotifyFavoritePresence),
+            ))
+            .add(settings::item(
+                fl!("settings-presence-debounce"),
+                text_input("30", &self.presence_debounce_input)
+                    .on_input(Message::PresenceDebounceInput)
+                    .width(120),
+            ))
+            .add(settings::item(
+                fl!("settings-notify-key-expiry"),
+                toggler(prefs.notify_on_key_expiry).on_toggle(Message::SetNotifyKeyExpiry),
+            ))
+            .add(settings::item(
+                fl!("settings-poll-interval"),
+                text_input("10", &self.poll_interval_input)
+                    .on_input(Message::PollIntervalInput)
+                    .width(120),
+            ))
+            .add(settings::item(
+                fl!("settings-key-expiry-days"),
+                text_input("7, 1", &self.key_expiry_input)
+                    .on_input(Message::KeyExpiryRemindersInput)
+                    .width(120),
+            ))
+            .add(settings::item(
+                fl!("settings-terminal"),
+                text_input("cosmic-term", &self.terminal_input)
+                    .on_input(Message::TerminalInput)
+                    .width(200),
+            ))
+            .add(settings::item(
+                fl!("settings-ssh-user"),
+                text_input(fl!("settings-ssh-user-placeholder"), &self.ssh_user_input)
+                    .on_input(Message::SshUserInput)
+                    .width(200),
+            ))
             .add(settings::item(
                 fl!("settings-download-dir"),
                 row![column![
@@ -1159,6 +4027,303 @@ impl Window {
                 .align_y(Alignment::Center),
             ));
 
-        column![elements].padding(4).into()
+        let mut schedule = column![
+            text(fl!("schedule-title")).size(14),
+            settings::item(
+                fl!("schedule-connect"),
+                text_input("9:00", &self.schedule_connect_input)
+                    .on_input(Message::ScheduleConnectInput)
+                    .width(120),
+            ),
+            settings::item(
+                fl!("schedule-disconnect"),
+                text_input("18:00", &self.schedule_disconnect_input)
+                    .on_input(Message::ScheduleDisconnectInput)
+                    .width(120),
+            ),
+        ]
+        .spacing(4);
+        if !self.schedule_error.is_empty() {
+            schedule = schedule.push(text(&self.schedule_error).size(11));
+        }
+
+        column![
+            elements,
+            self.view_accounts(),
+            self.view_shortcuts(),
+            self.view_settings_file(),
+            schedule,
+            self.view_network_rules(),
+            self.view_notification_styles(),
+            self.view_node_prefs(),
+            self.view_advanced_prefs()
+        ]
+        .spacing(8)
+        .padding(4)
+        .into()
+    }
+
+    /// Icon and sound per kind of notification, collapsed by default.
+    fn view_notification_styles(&self) -> Element<'_, Message> {
+        let header = button::text(if self.show_notification_styles {
+            format!("▾ {}", fl!("notification-styles-title"))
+        } else {
+            format!("▸ {}", fl!("notification-styles-title"))
+        })
+        .on_press(Message::ToggleNotificationStyles);
+
+        if !self.show_notification_styles {
+            return header.into();
+        }
+
+        let default_style = NotificationStyle::default();
+        let mut elements = list_column().list_item_padding(5);
+        for kind in NotificationKind::ALL {
+            let style = self
+                .preferences
+                .notification_styles
+                .get(&kind)
+                .unwrap_or(&default_style);
+            elements = elements.add(settings::item(
+                notification_kind_label(kind),
+                row![
+                    text_input(DEFAULT_ICON, style.icon.clone())
+                        .on_input(move |icon| Message::NotificationIconInput(kind, icon))
+                        .width(160),
+                    text_input(fl!("notification-sound-none"), style.sound.clone())
+                        .on_input(move |sound| Message::NotificationSoundInput(kind, sound))
+                        .width(160),
+                ]
+                .spacing(8),
+            ));
+        }
+
+        column![header, elements].spacing(4).into()
+    }
+
+    /// Less common daemon prefs, collapsed by default.
+    fn view_advanced_prefs(&self) -> Element<'_, Message> {
+        let header = button::text(if self.show_advanced {
+            format!("▾ {}", fl!("advanced-title"))
+        } else {
+            format!("▸ {}", fl!("advanced-title"))
+        })
+        .on_press(Message::ToggleAdvanced);
+
+        if !self.show_advanced {
+            return header.into();
+        }
+
+        let prefs = &self.state.prefs;
+        let netfilter_idx = NetfilterMode::ALL
+            .iter()
+            .position(|mode| *mode == prefs.netfilter_mode);
+
+        let text_pref = |label: String,
+                         placeholder: &'static str,
+                         value: String,
+                         on_input: fn(String) -> Message,
+                         on_apply: Message| {
+            settings::item(
+                label,
+                row![
+                    text_input(placeholder, value).on_input(on_input).width(200),
+                    button::standard(fl!("advanced-apply")).on_press(on_apply),
+                ]
+                .spacing(8)
+                .align_y(Alignment::Center),
+            )
+        };
+
+        let elements = list_column()
+            .list_item_padding(5)
+            .add(settings::item(
+                fl!("advanced-shields-up"),
+                toggler(prefs.shields_up)
+                    .on_toggle(|val| Message::SetPref(PrefEdit::ShieldsUp(val))),
+            ))
+            .add(settings::item(
+                fl!("advanced-no-snat"),
+                toggler(prefs.no_snat).on_toggle(|val| Message::SetPref(PrefEdit::NoSnat(val))),
+            ))
+            .add(settings::item(
+                fl!("advanced-netfilter"),
+                dropdown(
+                    &self.netfilter_mode_names,
+                    netfilter_idx,
+                    Message::NetfilterModeSelected,
+                ),
+            ))
+            .add(settings::item(
+                fl!("advanced-posture-checking"),
+                toggler(prefs.posture_checking)
+                    .on_toggle(|val| Message::SetPref(PrefEdit::PostureChecking(val))),
+            ))
+            .add(settings::item(
+                fl!("advanced-auto-update-check"),
+                toggler(prefs.auto_update.check)
+                    .on_toggle(|val| Message::SetPref(PrefEdit::AutoUpdateCheck(val))),
+            ))
+            .add(settings::item(
+                fl!("advanced-auto-update-apply"),
+                toggler(prefs.auto_update.apply.unwrap_or(false))
+                    .on_toggle(|val| Message::SetPref(PrefEdit::AutoUpdateApply(val))),
+            ))
+            .add(settings::item(
+                fl!("advanced-web-client"),
+                toggler(prefs.run_web_client)
+                    .on_toggle(|val| Message::SetPref(PrefEdit::RunWebClient(val))),
+            ))
+            .add(text_pref(
+                fl!("advanced-operator"),
+                "",
+                self.operator_user_draft
+                    .clone()
+                    .unwrap_or_else(|| prefs.operator_user.clone()),
+                Message::OperatorUserInput,
+                Message::ApplyOperatorUser,
+            ))
+            .add(text_pref(
+                fl!("advanced-control-url"),
+                "https://controlplane.tailscale.com",
+                self.control_url_draft
+                    .clone()
+                    .unwrap_or_else(|| prefs.control_url.clone()),
+                Message::ControlUrlInput,
+                Message::ApplyControlUrl,
+            ))
+            .add(settings::item(
+                fl!("advanced-logged-out"),
+                text(if prefs.logged_out {
+                    fl!("yes")
+                } else {
+                    fl!("no")
+                }),
+            ));
+
+        let mut col = column![header, elements].spacing(4);
+        if !self.advanced_status.is_empty() {
+            col = col.push(text(&self.advanced_status).size(11));
+        }
+        col.into()
+    }
+
+    /// Per-network rules, with a row to compose a new one.
+    fn view_network_rules(&self) -> Element<'_, Message> {
+        let mut col = column![text(fl!("rules-title")).size(14)].spacing(4);
+
+        if let Some(network) = &self.current_network {
+            col = col.push(text(format!("{}: {}", fl!("rules-current"), network.id)).size(11));
+        }
+        if self.preferences.network_rules.is_empty() {
+            col = col.push(text(fl!("rules-none")).size(12));
+        }
+        let active = self.matching_network_rule();
+        for (idx, rule) in self.preferences.network_rules.iter().enumerate() {
+            let mut label = network_rule_label(rule);
+            if active == Some(rule) {
+                label = format!("● {label}");
+            }
+            col = col.push(
+                row![
+                    text(label).size(12).width(Length::Fill),
+                    button::destructive(fl!("subnets-remove"))
+                        .on_press(Message::RemoveNetworkRule(idx))
+                        .width(Length::Shrink),
+                ]
+                .spacing(8)
+                .align_y(Alignment::Center),
+            );
+        }
+
+        let draft = &self.rule_draft;
+        let mut compose = row![dropdown(
+            &self.rule_match_names,
+            Some(draft.match_idx),
+            Message::RuleMatchSelected
+        )]
+        .spacing(8)
+        .align_y(Alignment::Center);
+        if draft.match_idx < 3 {
+            compose = compose.push(
+                text_input("", &draft.value)
+                    .on_input(Message::RuleValueInput)
+                    .width(140),
+            );
+        }
+        compose = compose.push(dropdown(
+            &self.rule_action_names,
+            Some(draft.action_idx),
+            Message::RuleActionSelected,
+        ));
+        if draft.action_idx == 0 {
+            let exit_idx = match &draft.exit_node {
+                ExitNodeRule::Keep => Some(0),
+                ExitNodeRule::Clear => Some(1),
+                // Indices past the first two are peer names; a peer that
+                // has since left the list shows as unselected.
+                ExitNodeRule::Use(name) => self
+                    .rule_exit_names
+                    .iter()
+                    .skip(2)
+                    .position(|n| n == name)
+                    .map(|idx| idx + 2),
+            };
+            compose = compose.push(dropdown(
+                &self.rule_exit_names,
+                exit_idx,
+                Message::RuleExitNodeSelected,
+            ));
+        }
+        compose =
+            compose.push(button::suggested(fl!("subnets-add")).on_press(Message::AddNetworkRule));
+
+        col.push(compose).into()
+    }
+
+    /// Daemon-side prefs for this node: advertised tags and app connector.
+    fn view_node_prefs(&self) -> Element<'_, Message> {
+        let state = &self.state;
+        let mut col = column![text(fl!("node-title")).size(14)].spacing(4);
+
+        col = col.push(settings::item(
+            fl!("node-app-connector"),
+            toggler(state.app_connector).on_toggle(Message::SetAppConnector),
+        ));
+
+        col = col.push(text(fl!("node-tags")).size(12));
+        if state.advertised_tags.is_empty() {
+            col = col.push(text(fl!("node-no-tags")).size(12));
+        }
+        for (idx, tag) in state.advertised_tags.iter().enumerate() {
+            col = col.push(
+                row![
+                    text(tag).width(Length::Fill),
+                    button::destructive(fl!("subnets-remove"))
+                        .on_press(Message::RemoveTag(idx))
+                        .width(Length::Shrink),
+                ]
+                .spacing(8)
+                .align_y(Alignment::Center),
+            );
+        }
+        col = col.push(
+            row![
+                text_input("tag:server", &self.tag_input)
+                    .on_input(Message::TagInput)
+                    .width(250),
+                button::suggested(fl!("subnets-add"))
+                    .on_press(Message::AddTag)
+                    .width(Length::Shrink),
+            ]
+            .spacing(8)
+            .align_y(Alignment::Center),
+        );
+
+        if !self.node_prefs_status.is_empty() {
+            col = col.push(text(&self.node_prefs_status).size(11));
+        }
+
+        col.into()
     }
 }